@@ -0,0 +1,99 @@
+use super::{Game, TextureId};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AnimationMode {
+    /// Starts over after the last frame.
+    #[default]
+    Loop,
+    /// Plays the frames back and forth.
+    PingPong,
+}
+
+/// Frames of an animated texture, played by the game clock.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Animation {
+    pub frames: Vec<TextureId>,
+    /// Tics each frame is shown for.
+    pub frame_tics: u32,
+    pub mode: AnimationMode,
+}
+
+impl Animation {
+    pub fn new(frames: Vec<TextureId>, frame_tics: u32) -> Self {
+        Self {
+            frames,
+            frame_tics,
+            mode: AnimationMode::Loop,
+        }
+    }
+
+    pub fn with_mode(mut self, mode: AnimationMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Frame shown `tics` tics after the start, `None` without frames.
+    pub fn frame(&self, tics: u32) -> Option<TextureId> {
+        let count = self.frames.len();
+        let step = (tics / self.frame_tics.max(1)) as usize;
+        let index = match self.mode {
+            AnimationMode::PingPong if count > 1 => {
+                let period = 2 * count - 2;
+                let i = step % period;
+                if i < count {
+                    i
+                } else {
+                    period - i
+                }
+            }
+            _ => step % count.max(1),
+        };
+        self.frames.get(index).copied()
+    }
+}
+
+impl Game {
+    /// Plays `animation` wherever texture `id` is drawn: walls, flats, skies and sprites.
+    /// `None` shows the texture itself again.
+    pub fn set_animation(&mut self, id: TextureId, animation: Option<Animation>) {
+        match animation {
+            Some(animation) => self.animations.insert(id, animation),
+            None => self.animations.remove(&id),
+        };
+    }
+
+    /// Texture drawn for `id` at the current tic of the level.
+    pub fn animated_texture(&self, id: TextureId) -> TextureId {
+        self.animations
+            .get(&id)
+            .and_then(|a| a.frame(self.progress.tics))
+            .unwrap_or(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Animation, AnimationMode};
+    use crate::{Color, Game, PixelBuffer, TicCmd};
+
+    #[test]
+    fn frames_follow_the_clock() {
+        let frames = |a: &Animation| (0..12).map(|t| a.frame(t * 2).unwrap()).collect::<Vec<_>>();
+        let looping = Animation::new(vec![1, 2, 3], 4);
+        assert_eq!(frames(&looping), [1, 1, 2, 2, 3, 3, 1, 1, 2, 2, 3, 3]);
+        let ping_pong = looping.clone().with_mode(AnimationMode::PingPong);
+        assert_eq!(frames(&ping_pong), [1, 1, 2, 2, 3, 3, 2, 2, 1, 1, 2, 2]);
+        assert_eq!(Animation::new(vec![], 4).frame(3), None);
+
+        let mut game = Game::new();
+        let mut lit = PixelBuffer::new(1, 1);
+        lit.fill(Color::white());
+        let dark = game.add_texture(PixelBuffer::new(1, 1));
+        let lit = game.add_texture(lit);
+        game.set_animation(dark, Some(Animation::new(vec![dark, lit], 2)));
+        assert_eq!(game.texture(dark).unwrap().data[0], Color::black());
+        game.tick(&TicCmd::default());
+        game.tick(&TicCmd::default());
+        assert_eq!(game.texture(dark).unwrap().data[0], Color::white());
+    }
+}
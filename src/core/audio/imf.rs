@@ -0,0 +1,259 @@
+use crate::Error;
+
+use super::{AudioSource, Opl2};
+
+/// Rate at which the original music driver consumes IMF delays.
+pub const IMF_RATE: u32 = 700;
+
+/// Single OPL register write, followed by a delay in [`IMF_RATE`] ticks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImfCommand {
+    pub reg: u8,
+    pub value: u8,
+    pub delay: u16,
+}
+
+/// AdLib music as a stream of OPL register writes.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Imf {
+    pub commands: Vec<ImfCommand>,
+}
+
+impl Imf {
+    pub fn new(commands: Vec<ImfCommand>) -> Self {
+        Self { commands }
+    }
+
+    /// Parses both IMF flavours: type 1 files (like the music chunks of the original data)
+    /// start with the length of the command data, type 0 files are bare commands.
+    pub fn from_bytes(data: &[u8]) -> crate::Result<Self> {
+        let declared = data
+            .get(..2)
+            .map(|v| u16::from_le_bytes([v[0], v[1]]) as usize)
+            .unwrap_or_default();
+        let commands = if declared != 0 && declared % 4 == 0 && declared <= data.len() - 2 {
+            &data[2..2 + declared]
+        } else {
+            data
+        };
+        if commands.len() % 4 != 0 {
+            return Err(Error::InvalidMusic(format!(
+                "{} bytes of commands is not a multiple of 4",
+                commands.len()
+            )));
+        }
+        Ok(Self::new(
+            commands
+                .chunks_exact(4)
+                .map(|c| ImfCommand {
+                    reg: c[0],
+                    value: c[1],
+                    delay: u16::from_le_bytes([c[2], c[3]]),
+                })
+                .collect(),
+        ))
+    }
+
+    /// Length of the song in [`IMF_RATE`] ticks.
+    pub fn ticks(&self) -> u32 {
+        self.commands.iter().map(|c| c.delay as u32).sum()
+    }
+}
+
+/// Plays an [`Imf`] song on an emulated [`Opl2`], looping at the end.
+#[derive(Debug, Clone)]
+pub struct ImfPlayer {
+    song: Imf,
+    opl: Opl2,
+    next: usize,
+    wait: u32,
+    /// Sub tick accumulator, in `IMF_RATE` units per output sample.
+    clock: u32,
+    looping: bool,
+    finished: bool,
+}
+
+impl ImfPlayer {
+    /// Player rendering `rate` samples per second, fails when `rate` is 0.
+    pub fn new(song: Imf, rate: u32) -> crate::Result<Self> {
+        Ok(Self {
+            song,
+            opl: Opl2::new(rate)?,
+            next: 0,
+            wait: 0,
+            clock: 0,
+            looping: true,
+            finished: false,
+        })
+    }
+
+    pub fn with_looping(mut self, looping: bool) -> Self {
+        self.looping = looping;
+        self
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Runs the commands due at the current tick.
+    ///
+    /// A looping song whose commands have no delay at all never advances time, so it
+    /// finishes after one full pass instead of spinning forever.
+    fn tick(&mut self) {
+        if self.wait > 0 {
+            self.wait -= 1;
+            return;
+        }
+        let mut ran = 0;
+        while self.wait == 0 && !self.finished {
+            if ran > self.song.commands.len() {
+                self.finished = true;
+                return;
+            }
+            let Some(cmd) = self.song.commands.get(self.next) else {
+                if self.looping && !self.song.commands.is_empty() {
+                    self.next = 0;
+                    continue;
+                }
+                self.finished = true;
+                return;
+            };
+            self.opl.write(cmd.reg, cmd.value);
+            self.wait = cmd.delay as u32;
+            self.next += 1;
+            ran += 1;
+        }
+        self.wait = self.wait.saturating_sub(1);
+    }
+
+    /// Renders mono samples at the OPL rate.
+    pub fn render_mono(&mut self, out: &mut [f32]) {
+        let rate = self.opl.rate();
+        for v in out.iter_mut() {
+            self.clock += IMF_RATE;
+            while self.clock >= rate {
+                self.clock -= rate;
+                self.tick();
+            }
+            *v = self.opl.sample();
+        }
+    }
+}
+
+impl AudioSource for ImfPlayer {
+    fn render(&mut self, out: &mut [f32]) {
+        let mut mono = vec![0f32; out.len() / 2];
+        self.render_mono(&mut mono);
+        for (frame, v) in out.chunks_exact_mut(2).zip(mono) {
+            frame[0] += v;
+            frame[1] += v;
+        }
+    }
+}
+
+/// Songs available to the game and which one each level plays.
+#[derive(Debug, Clone, Default)]
+pub struct MusicLibrary {
+    songs: Vec<Imf>,
+    levels: Vec<Option<usize>>,
+}
+
+impl MusicLibrary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_song(&mut self, song: Imf) -> usize {
+        self.songs.push(song);
+        self.songs.len() - 1
+    }
+
+    pub fn song(&self, id: usize) -> Option<&Imf> {
+        self.songs.get(id)
+    }
+
+    pub fn set_level_song(&mut self, level: u16, id: usize) {
+        let level = level as usize;
+        if self.levels.len() <= level {
+            self.levels.resize(level + 1, None);
+        }
+        self.levels[level] = Some(id);
+    }
+
+    pub fn level_song(&self, level: u16) -> Option<&Imf> {
+        self.levels
+            .get(level as usize)
+            .copied()
+            .flatten()
+            .and_then(|id| self.song(id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Imf, ImfCommand, ImfPlayer};
+
+    /// One A4-ish note on channel 0, held for 70 ticks (100ms) then released.
+    fn note() -> Imf {
+        let cmd = |reg, value, delay| ImfCommand { reg, value, delay };
+        Imf::new(vec![
+            cmd(0x20, 0x01, 0),
+            cmd(0x23, 0x01, 0),
+            cmd(0x40, 0x10, 0),
+            cmd(0x43, 0x00, 0),
+            cmd(0x60, 0xf4, 0),
+            cmd(0x63, 0xf4, 0),
+            cmd(0x80, 0x22, 0),
+            cmd(0x83, 0x22, 0),
+            cmd(0xa0, 0x41, 0),
+            cmd(0xb0, 0x32, 70),
+            cmd(0xb0, 0x12, 70),
+        ])
+    }
+
+    fn render(song: Imf) -> Vec<f32> {
+        let mut player = ImfPlayer::new(song, 22050).unwrap().with_looping(false);
+        let mut out = vec![0f32; 22050 / 5];
+        player.render_mono(&mut out);
+        out
+    }
+
+    #[test]
+    fn deterministic_output() {
+        let a = render(note());
+        assert_eq!(a, render(note()));
+        let peak = a[..2000].iter().fold(0f32, |m, v| m.max(v.abs()));
+        assert!(peak > 0.01, "note is silent");
+        assert!(a.iter().all(|v| v.abs() <= 1.0));
+    }
+
+    #[test]
+    fn parse_type1() {
+        let mut data = vec![8, 0];
+        data.extend([0xb0, 0x20, 10, 0, 0xb0, 0x00, 5, 0]);
+        data.extend([0, 0]); // trailing data
+        let song = Imf::from_bytes(&data).unwrap();
+        assert_eq!(song.commands.len(), 2);
+        assert_eq!(song.ticks(), 15);
+    }
+
+    #[test]
+    fn looping_without_delay_finishes() {
+        let song = Imf::new(vec![ImfCommand {
+            reg: 0xb0,
+            value: 0x32,
+            delay: 0,
+        }]);
+        let mut player = ImfPlayer::new(song, 22050).unwrap();
+        let mut out = vec![0f32; 64];
+        player.render_mono(&mut out);
+        assert!(player.is_finished());
+        assert!(!ImfPlayer::new(note(), 22050).unwrap().is_finished());
+    }
+
+    #[test]
+    fn zero_rate_is_rejected() {
+        assert!(ImfPlayer::new(note(), 0).is_err());
+    }
+}
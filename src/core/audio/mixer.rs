@@ -0,0 +1,339 @@
+use std::f32::consts::FRAC_PI_4;
+
+use crate::Error;
+
+/// Fractional bits of the voices' fixed point playback position.
+const FRAC_BITS: u32 = 16;
+
+/// Mono PCM sound, normalized to `[-1, 1]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    pub rate: u32,
+    pub data: Vec<f32>,
+}
+
+impl Sample {
+    pub fn new(rate: u32, data: Vec<f32>) -> Self {
+        Self { rate, data }
+    }
+
+    /// Unsigned 8 bit PCM, the format of the original digitized sounds.
+    pub fn from_u8(rate: u32, pcm: &[u8]) -> Self {
+        Self::new(
+            rate,
+            pcm.iter().map(|v| (*v as f32 - 128.0) / 128.0).collect(),
+        )
+    }
+
+    pub fn from_i16(rate: u32, pcm: &[i16]) -> Self {
+        Self::new(rate, pcm.iter().map(|v| *v as f32 / 32768.0).collect())
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.data.len() as f32 / self.rate as f32
+    }
+}
+
+pub type SampleId = usize;
+
+/// Continuous stream mixed on top of the voices, such as music.
+pub trait AudioSource: std::fmt::Debug + Send {
+    /// Adds interleaved stereo frames to `out`.
+    fn render(&mut self, out: &mut [f32]);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Voice {
+    sample: SampleId,
+    /// Position in the sample, 16.16 fixed point.
+    position: u64,
+    step: u64,
+    left: f32,
+    right: f32,
+    priority: u8,
+}
+
+/// Interleaved stereo frames waiting to be pulled by the audio output.
+#[derive(Debug, Clone)]
+pub struct RingBuffer {
+    data: Vec<f32>,
+    start: usize,
+    len: usize,
+}
+
+impl RingBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            data: vec![0f32; capacity],
+            start: 0,
+            len: 0,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn free(&self) -> usize {
+        self.capacity() - self.len
+    }
+
+    /// Appends as many values as fit, returns how many were written.
+    pub fn push(&mut self, values: &[f32]) -> usize {
+        let count = values.len().min(self.free());
+        for v in &values[..count] {
+            let idx = (self.start + self.len) % self.capacity();
+            self.data[idx] = *v;
+            self.len += 1;
+        }
+        count
+    }
+
+    /// Moves the oldest values into `out`, returns how many were read.
+    pub fn pop(&mut self, out: &mut [f32]) -> usize {
+        let count = out.len().min(self.len);
+        for v in out[..count].iter_mut() {
+            *v = self.data[self.start];
+            self.start = (self.start + 1) % self.capacity();
+            self.len -= 1;
+        }
+        count
+    }
+}
+
+/// Mixes up to N voices of [`Sample`]s into stereo frames, queued in a ring buffer until
+/// the audio output pulls them with [`Mixer::read`].
+///
+/// When every voice is busy, a new sound replaces the lowest priority one, but only if
+/// it has at least the same priority, like the original digitized sounds.
+#[derive(Debug)]
+pub struct Mixer {
+    rate: u32,
+    samples: Vec<Sample>,
+    voices: Vec<Option<Voice>>,
+    music: Option<Box<dyn AudioSource>>,
+    buffer: RingBuffer,
+    volume: f32,
+    music_volume: f32,
+}
+
+impl Mixer {
+    pub const CHANNELS: usize = 2;
+
+    /// `capacity` is the size of the ring buffer, in frames. Fails when `rate` is 0.
+    pub fn new(rate: u32, voices: usize, capacity: usize) -> crate::Result<Self> {
+        if rate == 0 {
+            return Err(Error::InvalidAudio("mixer rate is 0".to_string()));
+        }
+        Ok(Self {
+            rate,
+            samples: vec![],
+            voices: vec![None; voices],
+            music: None,
+            buffer: RingBuffer::new(capacity * Self::CHANNELS),
+            volume: 1f32,
+            music_volume: 1f32,
+        })
+    }
+
+    pub fn rate(&self) -> u32 {
+        self.rate
+    }
+
+    pub fn volume(&self) -> f32 {
+        self.volume
+    }
+
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = volume.clamp(0f32, 1f32);
+    }
+
+    pub fn music_volume(&self) -> f32 {
+        self.music_volume
+    }
+
+    pub fn set_music_volume(&mut self, volume: f32) {
+        self.music_volume = volume.clamp(0f32, 1f32);
+    }
+
+    /// Replaces the music stream, `None` stops it.
+    pub fn set_music(&mut self, music: Option<Box<dyn AudioSource>>) {
+        self.music = music;
+    }
+
+    pub fn add_sample(&mut self, sample: Sample) -> SampleId {
+        self.samples.push(sample);
+        self.samples.len() - 1
+    }
+
+    pub fn sample(&self, id: SampleId) -> Option<&Sample> {
+        self.samples.get(id)
+    }
+
+    /// Starts playing a sample, `pan` goes from -1 (left) to 1 (right).
+    /// Returns the voice used, or `None` when every voice plays something more important.
+    /// Fails on a sample with a rate of 0, which would never end.
+    pub fn play(
+        &mut self,
+        id: SampleId,
+        volume: f32,
+        pan: f32,
+        priority: u8,
+    ) -> crate::Result<Option<usize>> {
+        let Some(sample) = self.samples.get(id) else {
+            return Ok(None);
+        };
+        if sample.rate == 0 {
+            return Err(Error::InvalidAudio(format!(
+                "sample {} has a rate of 0",
+                id
+            )));
+        }
+        let slot = match self.voices.iter().position(|v| v.is_none()) {
+            Some(slot) => slot,
+            None => {
+                let Some((slot, lowest)) = self
+                    .voices
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(i, v)| v.map(|v| (i, v.priority)))
+                    .min_by_key(|(_, p)| *p)
+                else {
+                    return Ok(None);
+                };
+                if lowest > priority {
+                    return Ok(None);
+                }
+                slot
+            }
+        };
+        // constant power panning
+        let angle = (pan.clamp(-1f32, 1f32) + 1f32) * FRAC_PI_4;
+        let volume = volume.clamp(0f32, 1f32);
+        self.voices[slot] = Some(Voice {
+            sample: id,
+            position: 0,
+            step: ((sample.rate as u64) << FRAC_BITS) / self.rate as u64,
+            left: volume * angle.cos(),
+            right: volume * angle.sin(),
+            priority,
+        });
+        Ok(Some(slot))
+    }
+
+    pub fn stop(&mut self, voice: usize) {
+        if let Some(v) = self.voices.get_mut(voice) {
+            *v = None;
+        }
+    }
+
+    pub fn stop_all(&mut self) {
+        self.voices.iter_mut().for_each(|v| *v = None);
+    }
+
+    pub fn is_playing(&self, voice: usize) -> bool {
+        self.voices.get(voice).is_some_and(|v| v.is_some())
+    }
+
+    /// Number of mixed frames waiting in the ring buffer.
+    pub fn buffered(&self) -> usize {
+        self.buffer.len() / Self::CHANNELS
+    }
+
+    /// Mixes up to `frames` frames into the ring buffer, returns how many were mixed.
+    pub fn mix(&mut self, frames: usize) -> usize {
+        let frames = frames.min(self.buffer.free() / Self::CHANNELS);
+        let mut out = vec![0f32; frames * Self::CHANNELS];
+        self.render(&mut out);
+        self.buffer.push(&out) / Self::CHANNELS
+    }
+
+    /// Pulls interleaved stereo frames from the ring buffer. Frames are mixed when pulled,
+    /// only those it lacks, so new sounds wait no longer than the output's own buffering.
+    /// Returns the number of values written.
+    pub fn read(&mut self, out: &mut [f32]) -> usize {
+        let wanted = out.len() / Self::CHANNELS;
+        if self.buffered() < wanted {
+            self.mix(wanted - self.buffered());
+        }
+        self.buffer.pop(out)
+    }
+
+    /// Mixes the music and voices directly into `out` (interleaved stereo), bypassing the
+    /// ring buffer.
+    pub fn render(&mut self, out: &mut [f32]) {
+        out.iter_mut().for_each(|v| *v = 0f32);
+        if let Some(music) = self.music.as_mut() {
+            music.render(out);
+            out.iter_mut().for_each(|v| *v *= self.music_volume);
+        }
+        for slot in self.voices.iter_mut() {
+            let Some(voice) = slot.as_mut() else {
+                continue;
+            };
+            let data = &self.samples[voice.sample].data;
+            for frame in out.chunks_exact_mut(Self::CHANNELS) {
+                let idx = (voice.position >> FRAC_BITS) as usize;
+                if idx >= data.len() {
+                    *slot = None;
+                    break;
+                }
+                // linear interpolation between the two closest sample points
+                let frac =
+                    (voice.position & ((1 << FRAC_BITS) - 1)) as f32 / (1 << FRAC_BITS) as f32;
+                let next = data.get(idx + 1).copied().unwrap_or(data[idx]);
+                let v = data[idx] + (next - data[idx]) * frac;
+                frame[0] += v * voice.left;
+                frame[1] += v * voice.right;
+                voice.position += voice.step;
+            }
+        }
+        for v in out.iter_mut() {
+            *v = (*v * self.volume).clamp(-1f32, 1f32);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Mixer, Sample};
+
+    #[test]
+    fn mix_panned_voices() {
+        let mut mixer = Mixer::new(8, 2, 16).unwrap();
+        let id = mixer.add_sample(Sample::new(8, vec![0.5, 0.5, -0.5, -0.5]));
+        mixer.play(id, 1.0, -1.0, 0).unwrap().unwrap();
+        mixer.play(id, 0.5, 1.0, 0).unwrap().unwrap();
+        let mut out = [0f32; 12];
+        assert_eq!(mixer.read(&mut out), 12);
+        let expected = [
+            0.5, 0.25, 0.5, 0.25, -0.5, -0.25, -0.5, -0.25, 0.0, 0.0, 0.0, 0.0,
+        ];
+        for (v, e) in out.iter().zip(expected) {
+            assert!((v - e).abs() < 1e-6, "{:?} != {:?}", out, expected);
+        }
+        assert!(!mixer.is_playing(0) && !mixer.is_playing(1));
+    }
+
+    #[test]
+    fn priority_stealing() {
+        let mut mixer = Mixer::new(8, 1, 16).unwrap();
+        let id = mixer.add_sample(Sample::new(8, vec![0.0; 8]));
+        assert_eq!(mixer.play(id, 1.0, 0.0, 5).unwrap(), Some(0));
+        assert_eq!(mixer.play(id, 1.0, 0.0, 4).unwrap(), None);
+        assert_eq!(mixer.play(id, 1.0, 0.0, 5).unwrap(), Some(0));
+        assert!(Mixer::new(0, 1, 16).is_err());
+        // a sample without a rate would hold its voice forever
+        let silent = mixer.add_sample(Sample::new(0, vec![0.0; 8]));
+        assert!(mixer.play(silent, 1.0, 0.0, 9).is_err());
+        assert_eq!(mixer.play(id, 1.0, 0.0, 5).unwrap(), Some(0));
+    }
+}
@@ -0,0 +1,7 @@
+pub mod imf;
+pub mod mixer;
+pub mod opl;
+
+pub use imf::*;
+pub use mixer::*;
+pub use opl::*;
@@ -0,0 +1,356 @@
+use std::f32::consts::PI;
+
+use crate::Error;
+
+/// Master clock of the chip divided by 72, the rate it natively produces samples at.
+pub const OPL2_RATE: u32 = 49716;
+
+const CHANNELS: usize = 9;
+const OPERATORS: usize = CHANNELS * 2;
+const WAVE_LEN: usize = 1024;
+
+/// Frequency multipliers, indexed by the MULT field.
+const MULTIPLIERS: [f32; 16] = [
+    0.5, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, 10.0, 10.0, 12.0, 12.0, 15.0, 15.0,
+];
+/// Operator of each register offset, holes in the layout are `None`.
+#[rustfmt::skip]
+const OPERATOR_OFFSETS: [Option<usize>; 0x16] = [
+    Some(0), Some(1), Some(2), Some(3), Some(4), Some(5), None, None,
+    Some(6), Some(7), Some(8), Some(9), Some(10), Some(11), None, None,
+    Some(12), Some(13), Some(14), Some(15), Some(16), Some(17),
+];
+/// Operators of each channel, modulator first.
+#[rustfmt::skip]
+const CHANNEL_OPERATORS: [(usize, usize); CHANNELS] = [
+    (0, 3), (1, 4), (2, 5), (6, 9), (7, 10), (8, 11), (12, 15), (13, 16), (14, 17),
+];
+
+/// Silence, in decibels of attenuation.
+const MAX_ATTENUATION: f32 = 96.0;
+/// Time to go through the whole attenuation range at the slowest (non zero) rates.
+const ATTACK_MS: f32 = 2826.0;
+const DECAY_MS: f32 = 39280.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum Stage {
+    Attack,
+    Decay,
+    Sustain,
+    #[default]
+    Release,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Operator {
+    tremolo: bool,
+    vibrato: bool,
+    sustained: bool,
+    ksr: bool,
+    multiplier: usize,
+    total_level: u8,
+    attack: u8,
+    decay: u8,
+    sustain: u8,
+    release: u8,
+    waveform: u8,
+
+    phase: f32,
+    stage: Stage,
+    attenuation: f32,
+    output: f32,
+}
+
+impl Operator {
+    /// Attenuation change per sample for a 4 bit rate, scaled by the channel key code.
+    fn rate_step(&self, rate: u8, duration_ms: f32, key_code: u8, sample_rate: u32) -> f32 {
+        if rate == 0 {
+            return 0f32;
+        }
+        let offset = if self.ksr { key_code } else { key_code >> 2 };
+        let effective = (rate as u32 * 4 + offset as u32).min(63);
+        let ms = duration_ms / 2f32.powf((effective as f32 - 4f32) / 4f32);
+        MAX_ATTENUATION / (ms * sample_rate as f32 / 1000f32).max(1f32)
+    }
+
+    fn update_envelope(&mut self, key_code: u8, sample_rate: u32) {
+        match self.stage {
+            Stage::Attack => {
+                if self.attack == 15 {
+                    self.attenuation = 0f32;
+                } else {
+                    self.attenuation -=
+                        self.rate_step(self.attack, ATTACK_MS, key_code, sample_rate);
+                }
+                if self.attenuation <= 0f32 {
+                    self.attenuation = 0f32;
+                    self.stage = Stage::Decay;
+                }
+            }
+            Stage::Decay => {
+                let sustain_level = if self.sustain == 15 {
+                    93f32
+                } else {
+                    self.sustain as f32 * 3f32
+                };
+                self.attenuation += self.rate_step(self.decay, DECAY_MS, key_code, sample_rate);
+                if self.attenuation >= sustain_level {
+                    self.attenuation = sustain_level;
+                    self.stage = Stage::Sustain;
+                }
+            }
+            Stage::Sustain => {
+                // percussive sounds keep fading at the release rate while keyed on
+                if !self.sustained {
+                    self.attenuation +=
+                        self.rate_step(self.release, DECAY_MS, key_code, sample_rate);
+                }
+            }
+            Stage::Release => {
+                self.attenuation += self.rate_step(self.release, DECAY_MS, key_code, sample_rate);
+            }
+        }
+        self.attenuation = self.attenuation.min(MAX_ATTENUATION);
+    }
+
+    fn key_on(&mut self) {
+        self.phase = 0f32;
+        self.stage = Stage::Attack;
+    }
+
+    fn key_off(&mut self) {
+        self.stage = Stage::Release;
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Channel {
+    fnum: u16,
+    block: u8,
+    key_on: bool,
+    feedback: u8,
+    additive: bool,
+}
+
+impl Channel {
+    fn frequency(&self) -> f32 {
+        self.fnum as f32 * OPL2_RATE as f32 / (1u32 << (20 - self.block)) as f32
+    }
+
+    fn key_code(&self) -> u8 {
+        (self.block << 1) | ((self.fnum >> 9) & 1) as u8
+    }
+}
+
+/// Yamaha YM3812 (OPL2) FM synthesis, as driven by the original AdLib music.
+///
+/// This is a floating point approximation of the chip: two operators per channel with
+/// ADSR envelopes, feedback, tremolo, vibrato and the four waveforms. Rhythm mode and
+/// key scale levels are not emulated.
+#[derive(Debug, Clone)]
+pub struct Opl2 {
+    rate: u32,
+    registers: [u8; 256],
+    operators: [Operator; OPERATORS],
+    channels: [Channel; CHANNELS],
+    sine: Vec<f32>,
+    lfo_phase: f32,
+}
+
+impl Opl2 {
+    /// Emulator producing `rate` samples per second, fails when `rate` is 0.
+    pub fn new(rate: u32) -> crate::Result<Self> {
+        if rate == 0 {
+            return Err(Error::InvalidAudio("OPL output rate is 0".to_string()));
+        }
+        Ok(Self::with_rate(rate))
+    }
+
+    fn with_rate(rate: u32) -> Self {
+        Self {
+            rate,
+            registers: [0; 256],
+            operators: [Operator {
+                attenuation: MAX_ATTENUATION,
+                ..Default::default()
+            }; OPERATORS],
+            channels: [Channel::default(); CHANNELS],
+            sine: (0..WAVE_LEN)
+                .map(|i| (i as f32 * 2f32 * PI / WAVE_LEN as f32).sin())
+                .collect(),
+            lfo_phase: 0f32,
+        }
+    }
+
+    pub fn rate(&self) -> u32 {
+        self.rate
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::with_rate(self.rate);
+    }
+
+    pub fn register(&self, reg: u8) -> u8 {
+        self.registers[reg as usize]
+    }
+
+    pub fn write(&mut self, reg: u8, value: u8) {
+        self.registers[reg as usize] = value;
+        let op = |base: u8| {
+            OPERATOR_OFFSETS
+                .get((reg - base) as usize)
+                .copied()
+                .flatten()
+        };
+        match reg {
+            0x20..=0x35 => {
+                if let Some(op) = op(0x20) {
+                    let op = &mut self.operators[op];
+                    op.tremolo = value & 0x80 != 0;
+                    op.vibrato = value & 0x40 != 0;
+                    op.sustained = value & 0x20 != 0;
+                    op.ksr = value & 0x10 != 0;
+                    op.multiplier = (value & 0x0f) as usize;
+                }
+            }
+            0x40..=0x55 => {
+                if let Some(op) = op(0x40) {
+                    self.operators[op].total_level = value & 0x3f;
+                }
+            }
+            0x60..=0x75 => {
+                if let Some(op) = op(0x60) {
+                    self.operators[op].attack = value >> 4;
+                    self.operators[op].decay = value & 0x0f;
+                }
+            }
+            0x80..=0x95 => {
+                if let Some(op) = op(0x80) {
+                    self.operators[op].sustain = value >> 4;
+                    self.operators[op].release = value & 0x0f;
+                }
+            }
+            0xa0..=0xa8 => {
+                let ch = &mut self.channels[(reg - 0xa0) as usize];
+                ch.fnum = (ch.fnum & 0x300) | value as u16;
+            }
+            0xb0..=0xb8 => {
+                let idx = (reg - 0xb0) as usize;
+                let ch = &mut self.channels[idx];
+                ch.fnum = (ch.fnum & 0xff) | ((value as u16 & 0x03) << 8);
+                ch.block = (value >> 2) & 0x07;
+                let key_on = value & 0x20 != 0;
+                if key_on != ch.key_on {
+                    ch.key_on = key_on;
+                    let (a, b) = CHANNEL_OPERATORS[idx];
+                    for op in [a, b] {
+                        if key_on {
+                            self.operators[op].key_on();
+                        } else {
+                            self.operators[op].key_off();
+                        }
+                    }
+                }
+            }
+            0xc0..=0xc8 => {
+                let ch = &mut self.channels[(reg - 0xc0) as usize];
+                ch.feedback = (value >> 1) & 0x07;
+                ch.additive = value & 0x01 != 0;
+            }
+            0xe0..=0xf5 => {
+                if let Some(op) = op(0xe0) {
+                    self.operators[op].waveform = value & 0x03;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn waveform_enabled(&self) -> bool {
+        self.registers[0x01] & 0x20 != 0
+    }
+
+    fn wave(&self, waveform: u8, phase: f32) -> f32 {
+        let phase = phase.rem_euclid(1f32);
+        let v = self.sine[(phase * WAVE_LEN as f32) as usize % WAVE_LEN];
+        match if self.waveform_enabled() { waveform } else { 0 } {
+            0 => v,
+            1 => v.max(0f32),
+            2 => v.abs(),
+            _ => {
+                if phase.rem_euclid(0.5) < 0.25 {
+                    v.abs()
+                } else {
+                    0f32
+                }
+            }
+        }
+    }
+
+    /// Runs an operator for one sample, `modulation` is a phase offset in cycles.
+    fn operator(
+        &mut self,
+        idx: usize,
+        ch: &Channel,
+        modulation: f32,
+        tremolo: f32,
+        vibrato: f32,
+    ) -> f32 {
+        let key_code = ch.key_code();
+        let rate = self.rate;
+        let op = &mut self.operators[idx];
+        op.update_envelope(key_code, rate);
+        let mut attenuation = op.attenuation + op.total_level as f32 * 0.75;
+        if op.tremolo {
+            attenuation += tremolo;
+        }
+        let mut freq = ch.frequency() * MULTIPLIERS[op.multiplier];
+        if op.vibrato {
+            freq *= vibrato;
+        }
+        let phase = op.phase;
+        op.phase = (op.phase + freq / rate as f32).rem_euclid(1f32);
+        let waveform = op.waveform;
+        if attenuation >= MAX_ATTENUATION {
+            self.operators[idx].output = 0f32;
+            return 0f32;
+        }
+        let out = self.wave(waveform, phase + modulation) * 10f32.powf(-attenuation / 20f32);
+        self.operators[idx].output = out;
+        out
+    }
+
+    /// Produces the next mono sample.
+    pub fn sample(&mut self) -> f32 {
+        // tremolo at 3.7Hz, vibrato at 6.1Hz, depths selected by register 0xBD
+        self.lfo_phase = (self.lfo_phase + 1f32 / self.rate as f32).rem_euclid(100f32);
+        let depth = self.registers[0xbd];
+        let tremolo_db = if depth & 0x80 != 0 { 4.8 } else { 1.0 };
+        let tremolo = (1f32 + (self.lfo_phase * 3.7 * 2f32 * PI).sin()) * 0.5 * tremolo_db;
+        let vibrato_cents = if depth & 0x40 != 0 { 14f32 } else { 7f32 };
+        let vibrato = 2f32.powf((self.lfo_phase * 6.1 * 2f32 * PI).sin() * vibrato_cents / 1200f32);
+
+        let mut out = 0f32;
+        for (idx, (m, c)) in CHANNEL_OPERATORS.iter().enumerate() {
+            let ch = self.channels[idx];
+            let feedback = if ch.feedback == 0 {
+                0f32
+            } else {
+                self.operators[*m].output * 2f32.powf(ch.feedback as f32 - 6f32)
+            };
+            let modulator = self.operator(*m, &ch, feedback, tremolo, vibrato);
+            if ch.additive {
+                out += modulator + self.operator(*c, &ch, 0f32, tremolo, vibrato);
+            } else {
+                out += self.operator(*c, &ch, modulator * 2f32, tremolo, vibrato);
+            }
+        }
+        out / CHANNELS as f32 * 2f32
+    }
+
+    pub fn render(&mut self, out: &mut [f32]) {
+        for v in out.iter_mut() {
+            *v = self.sample();
+        }
+    }
+}
@@ -0,0 +1,198 @@
+use super::{Color, DoorLock, EntityKind, Game, PixelBuffer, Tile, Vec2};
+
+/// Colors of the full screen automap.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AutomapStyle {
+    pub background: Color,
+    /// Explored floor.
+    pub floor: Color,
+    pub wall: Color,
+    pub door: Color,
+    pub locked_door: Color,
+    /// Elevator doors, leading to the next level.
+    pub exit: Color,
+    pub gold_key: Color,
+    pub silver_key: Color,
+    pub player: Color,
+}
+
+impl Default for AutomapStyle {
+    fn default() -> Self {
+        Self {
+            background: Color::black(),
+            floor: Color::new(40, 40, 40, 255),
+            wall: Color::new(160, 160, 160, 255),
+            door: Color::new(0, 160, 160, 255),
+            locked_door: Color::new(200, 160, 0, 255),
+            exit: Color::new(0, 200, 0, 255),
+            gold_key: Color::new(255, 215, 0, 255),
+            silver_key: Color::new(200, 200, 220, 255),
+            player: Color::red(),
+        }
+    }
+}
+
+/// Full screen map of the explored tiles, north up.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Automap {
+    pub visible: bool,
+    /// World position shown at the center of the screen, ignored when following the player.
+    pub center: Vec2,
+    /// Pixels per world tile.
+    pub zoom: f32,
+    pub follow_player: bool,
+    pub style: AutomapStyle,
+}
+
+impl Default for Automap {
+    fn default() -> Self {
+        Self {
+            visible: false,
+            center: Vec2::new(0f32, 0f32),
+            zoom: 8f32,
+            follow_player: true,
+            style: AutomapStyle::default(),
+        }
+    }
+}
+
+impl Automap {
+    pub const MIN_ZOOM: f32 = 1f32;
+    pub const MAX_ZOOM: f32 = 64f32;
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    /// Moves the view by `delta` pixels, detaching it from the player.
+    pub fn pan(&mut self, from: Vec2, delta: Vec2) {
+        if self.follow_player {
+            self.center = from;
+            self.follow_player = false;
+        }
+        self.center += delta.scaled(1f32 / self.zoom);
+    }
+
+    pub fn zoom_by(&mut self, factor: f32) {
+        if factor > 0f32 {
+            self.zoom = (self.zoom * factor).clamp(Self::MIN_ZOOM, Self::MAX_ZOOM);
+        }
+    }
+
+    /// Centers the view back on the player.
+    pub fn recenter(&mut self) {
+        self.follow_player = true;
+    }
+}
+
+impl Game {
+    /// World position at the center of the automap.
+    pub fn automap_center(&self) -> Vec2 {
+        if self.automap.follow_player {
+            self.player.position
+        } else {
+            self.automap.center
+        }
+    }
+
+    /// Draws the explored part of the level over the whole buffer.
+    pub fn render_automap(&self, buf: &mut PixelBuffer) {
+        let map = &self.automap;
+        let style = &map.style;
+        let tiles = self.tiles();
+        let center = self.automap_center();
+        let half_size = Vec2::new(buf.width as f32, buf.height as f32).scaled(0.5);
+        let to_world = |x: usize, y: usize| {
+            let screen = Vec2::new(x as f32 + 0.5, y as f32 + 0.5) - half_size;
+            center + screen.scaled(1f32 / map.zoom)
+        };
+        let half_pixel = 0.5 / map.zoom;
+        for y in 0..buf.height {
+            for x in 0..buf.width {
+                let world = to_world(x, y);
+                let tile = world.floor::<i32>();
+                let color = if !self.is_explored(tile) {
+                    style.background
+                } else {
+                    match tiles.as_ref().and_then(|t| t.get(tile)) {
+                        Some(Tile::Wall(w)) if self.walls[*w].overlaps(world, half_pixel) => {
+                            style.wall
+                        }
+                        Some(Tile::Door(d)) => match self.doors[*d].lock {
+                            DoorLock::None => style.door,
+                            DoorLock::Elevator => style.exit,
+                            _ => style.locked_door,
+                        },
+                        _ => style.floor,
+                    }
+                };
+                let _ = buf.put(x, y, color);
+            }
+        }
+
+        let radius = (map.zoom / 4f32).max(1f32) as isize;
+        let mut marker = |pos: Vec2, color: Color| {
+            let screen = ((pos - center).scaled(map.zoom) + half_size).floor::<isize>();
+            buf.fill_circle(screen, radius, color);
+        };
+        for entity in self.entities.iter().filter(|e| e.is_alive()) {
+            if !self.is_explored(entity.position.floor::<i32>()) {
+                continue;
+            }
+            match entity.kind {
+                EntityKind::GoldKey => marker(entity.position, style.gold_key),
+                EntityKind::SilverKey => marker(entity.position, style.silver_key),
+                _ => {}
+            }
+        }
+        marker(self.player.position, style.player);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Game, PixelBuffer, TicCmd, Vec2, Wall, WallKind};
+
+    #[test]
+    fn only_explored_tiles_are_drawn() {
+        // a corridor running east, the player looking down it, a wall behind its back
+        let mut game = Game::new()
+            .with_player_position(Vec2::new(1.5, 0.5))
+            .with_player_direction(Vec2::new(1.0, 0.0))
+            .with_walls(&[
+                Wall::new(WallKind::Basic, Vec2::new(-2, 0)),
+                Wall::new(WallKind::Basic, Vec2::new(5, 0)),
+            ]);
+        game.tick(&TicCmd::default());
+        assert!(game.is_explored(Vec2::new(4, 0)));
+        assert!(game.is_explored(Vec2::new(5, 0)));
+        assert!(!game.is_explored(Vec2::new(-2, 0)));
+
+        game.automap.zoom = 10.0;
+        let mut buf = PixelBuffer::new(100, 20);
+        game.render_automap(&mut buf);
+        let style = game.automap.style;
+        // the player is at the center, tiles are 10 pixels wide
+        assert_eq!(buf.get(86, 10), Some(&style.wall));
+        assert_eq!(buf.get(16, 10), Some(&style.background));
+    }
+
+    #[test]
+    fn exploring_waits_for_a_new_view() {
+        let mut game = Game::new()
+            .with_player_position(Vec2::new(1.5, 0.5))
+            .with_player_direction(Vec2::new(1.0, 0.0))
+            .with_walls(&[
+                Wall::new(WallKind::Basic, Vec2::new(-2, 0)),
+                Wall::new(WallKind::Basic, Vec2::new(5, 0)),
+            ]);
+        game.tick(&TicCmd::default());
+        game.explored.clear();
+        game.tick(&TicCmd::default());
+        assert!(game.explored.is_empty());
+
+        game.player.direction = Vec2::new(-1.0, 0.0);
+        game.tick(&TicCmd::default());
+        assert!(game.is_explored(Vec2::new(-2, 0)));
+    }
+}
@@ -0,0 +1,207 @@
+use super::{Color, Error, PixelBuffer, Rect, Vec2};
+
+/// How source pixels are combined with the destination ones.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BlendMode {
+    /// Source pixels replace the destination ones.
+    #[default]
+    Opaque,
+    /// Like `Opaque`, but pixels with a zero alpha (such as [`Color::transparent`]) are
+    /// skipped, the way the original sprites are masked.
+    AlphaKey,
+    /// Source over destination, weighted by the source alpha.
+    Alpha,
+    /// Source color weighted by its alpha is added to the destination, for lights and
+    /// muzzle flashes.
+    Additive,
+}
+
+impl BlendMode {
+    /// Combines `src` over `dst`, `None` leaves the destination untouched.
+    pub fn blend(&self, src: Color, dst: Color) -> Option<Color> {
+        let mix = |s: u8, d: u8, a: u8| {
+            ((s as u32 * a as u32 + d as u32 * (255 - a as u32) + 127) / 255) as u8
+        };
+        match self {
+            Self::Opaque => Some(src),
+            Self::AlphaKey => (src.a != 0).then_some(src),
+            Self::Alpha => match src.a {
+                0 => None,
+                255 => Some(src),
+                a => Some(Color::new(
+                    mix(src.r, dst.r, a),
+                    mix(src.g, dst.g, a),
+                    mix(src.b, dst.b, a),
+                    mix(255, dst.a, a),
+                )),
+            },
+            Self::Additive => {
+                let add =
+                    |s: u8, d: u8| d.saturating_add(((s as u32 * src.a as u32 + 127) / 255) as u8);
+                Some(Color::new(
+                    add(src.r, dst.r),
+                    add(src.g, dst.g),
+                    add(src.b, dst.b),
+                    dst.a,
+                ))
+            }
+        }
+    }
+}
+
+/// Parameters of [`PixelBuffer::blit`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Blit {
+    /// Part of the source to copy, the whole source when `None`.
+    pub src: Option<Rect<isize>>,
+    /// Top left corner in the destination.
+    pub dest: Vec2<isize>,
+    /// Size in the destination, nearest neighbour scaled. The source size when `None`.
+    pub size: Option<Vec2<usize>>,
+    /// Mirrors the source horizontally.
+    pub flip_x: bool,
+    pub mode: BlendMode,
+}
+
+impl Blit {
+    pub fn new(dest: Vec2<isize>) -> Self {
+        Self {
+            dest,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_src(mut self, src: Rect<isize>) -> Self {
+        self.src = Some(src);
+        self
+    }
+
+    pub fn with_size(mut self, size: Vec2<usize>) -> Self {
+        self.size = Some(size);
+        self
+    }
+
+    pub fn with_flip_x(mut self, flip_x: bool) -> Self {
+        self.flip_x = flip_x;
+        self
+    }
+
+    pub fn with_mode(mut self, mode: BlendMode) -> Self {
+        self.mode = mode;
+        self
+    }
+}
+
+impl PixelBuffer {
+    /// Image from tightly packed RGBA8 data.
+    pub fn from_rgba(width: usize, height: usize, data: &[u8]) -> crate::Result<Self> {
+        if data.len() != width * height * 4 {
+            return Err(Error::InvalidImage(format!(
+                "{} bytes of RGBA data for a {}x{} image",
+                data.len(),
+                width,
+                height
+            )));
+        }
+        let mut ret = Self::new(width, height);
+        for (dst, src) in ret.data.iter_mut().zip(data.chunks_exact(4)) {
+            *dst = Color::new(src[0], src[1], src[2], src[3]);
+        }
+        Ok(ret)
+    }
+
+    pub fn bounds(&self) -> Rect<isize> {
+        Rect::new(
+            Vec2::new(0, 0),
+            Vec2::new(self.width as isize, self.height as isize),
+        )
+    }
+
+    /// Copies (part of) `src` into this buffer, clipped to the clip rect.
+    pub fn blit(&mut self, src: &PixelBuffer, blit: &Blit) {
+        let src_rect = blit.src.unwrap_or(src.bounds());
+        if src_rect.is_empty() {
+            return;
+        }
+        let src_size = src_rect.area();
+        let size = blit
+            .size
+            .unwrap_or(src_size.cast::<usize>())
+            .cast::<isize>();
+        let dest_rect = Rect::new(blit.dest, blit.dest + size);
+        let Some(visible) = dest_rect.intersection(&self.clip()) else {
+            return;
+        };
+        for y in visible.start().y..visible.end().y {
+            // nearest neighbour: the source pixel under the center of the destination one
+            let sy = src_rect.start().y + ((2 * (y - blit.dest.y) + 1) * src_size.y) / (2 * size.y);
+            for x in visible.start().x..visible.end().x {
+                let mut sx = ((2 * (x - blit.dest.x) + 1) * src_size.x) / (2 * size.x);
+                if blit.flip_x {
+                    sx = src_size.x - 1 - sx;
+                }
+                let sx = src_rect.start().x + sx;
+                if !src.bounds().contains(Vec2::new(sx, sy)) {
+                    continue;
+                }
+                let color = src.data[sy as usize * src.width + sx as usize];
+                let idx = y as usize * self.width + x as usize;
+                if let Some(color) = blit.mode.blend(color, self.data[idx]) {
+                    self.data[idx] = color;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BlendMode, Blit};
+    use crate::{Color, PixelBuffer, Rect, Vec2};
+
+    /// 2x1 image: red then transparent.
+    fn sprite() -> PixelBuffer {
+        PixelBuffer::from_rgba(2, 1, &[255, 0, 0, 255, 0, 0, 0, 0]).unwrap()
+    }
+
+    #[test]
+    fn scaled_flipped_and_clipped() {
+        let mut buf = PixelBuffer::new(3, 2);
+        buf.blit(
+            &sprite(),
+            &Blit::new(Vec2::new(-1, 0))
+                .with_size(Vec2::new(4, 2))
+                .with_flip_x(true)
+                .with_mode(BlendMode::AlphaKey),
+        );
+        // flipped and doubled: transparent, transparent, red, red, starting at x = -1
+        let row = |y| (0..3).map(|x| *buf.get(x, y).unwrap()).collect::<Vec<_>>();
+        let expected = vec![Color::black(), Color::red(), Color::red()];
+        assert_eq!(row(0), expected);
+        assert_eq!(row(1), expected);
+    }
+
+    #[test]
+    fn source_rect_and_blend_modes() {
+        let mut buf = PixelBuffer::new(1, 1);
+        buf.fill(Color::new(100, 100, 100, 255));
+        let half_white = PixelBuffer::from_rgba(1, 1, &[255, 255, 255, 128]).unwrap();
+        buf.blit(
+            &half_white,
+            &Blit::new(Vec2::new(0, 0)).with_mode(BlendMode::Alpha),
+        );
+        assert_eq!(buf.get(0, 0), Some(&Color::new(178, 178, 178, 255)));
+        buf.blit(
+            &half_white,
+            &Blit::new(Vec2::new(0, 0)).with_mode(BlendMode::Additive),
+        );
+        assert_eq!(buf.get(0, 0), Some(&Color::new(255, 255, 255, 255)));
+
+        buf.blit(
+            &sprite(),
+            &Blit::new(Vec2::new(0, 0)).with_src(Rect::new(Vec2::new(1, 0), Vec2::new(2, 1))),
+        );
+        assert_eq!(buf.get(0, 0), Some(&Color::transparent()));
+        assert!(PixelBuffer::from_rgba(2, 2, &[0; 3]).is_err());
+    }
+}
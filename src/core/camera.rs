@@ -0,0 +1,72 @@
+use super::Player;
+
+/// Projection of the 3D view. The vertical field of view stays constant, so wider
+/// screens see more on the sides instead of being cropped on the top and bottom.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Camera {
+    /// Horizontal field of view at [`Camera::REFERENCE_ASPECT`], in degrees.
+    pub fov: f32,
+    /// Width over height of the viewport.
+    pub aspect: f32,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self::new(Player::FOV, Self::REFERENCE_ASPECT)
+    }
+}
+
+impl Camera {
+    /// Aspect ratio `fov` is given for, the original 320x200 view stretched to 4:3.
+    pub const REFERENCE_ASPECT: f32 = 4.0 / 3.0;
+
+    pub fn new(fov: f32, aspect: f32) -> Self {
+        Self { fov, aspect }
+    }
+
+    pub fn with_fov(mut self, fov: f32) -> Self {
+        self.fov = fov;
+        self
+    }
+
+    /// Updates the aspect ratio for a `width`x`height` viewport, ignored when empty.
+    pub fn set_viewport(&mut self, width: usize, height: usize) {
+        if width > 0 && height > 0 {
+            self.aspect = width as f32 / height as f32;
+        }
+    }
+
+    /// Vertical field of view, in degrees.
+    pub fn vertical_fov(&self) -> f32 {
+        let half = (self.fov.to_radians() * 0.5).tan() / Self::REFERENCE_ASPECT;
+        (half.atan() * 2.0).to_degrees()
+    }
+
+    /// Horizontal field of view for the current aspect ratio, in degrees.
+    pub fn horizontal_fov(&self) -> f32 {
+        (self.plane_scale().atan() * 2.0).to_degrees()
+    }
+
+    /// Half width of the projection plane at distance 1, the length of the camera plane
+    /// vector used to cast one ray per screen column.
+    pub fn plane_scale(&self) -> f32 {
+        (self.vertical_fov().to_radians() * 0.5).tan() * self.aspect
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Camera;
+
+    #[test]
+    fn wider_viewports_see_more() {
+        let mut camera = Camera::new(66.0, Camera::REFERENCE_ASPECT);
+        assert!((camera.horizontal_fov() - 66.0).abs() < 1e-3);
+        let vertical = camera.vertical_fov();
+        camera.set_viewport(1920, 1080);
+        assert!(camera.horizontal_fov() > 66.0);
+        assert!((camera.vertical_fov() - vertical).abs() < 1e-3);
+        camera.set_viewport(0, 0);
+        assert!((camera.aspect - 16.0 / 9.0).abs() < 1e-6);
+    }
+}
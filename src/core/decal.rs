@@ -0,0 +1,81 @@
+use std::collections::VecDeque;
+
+use super::{Game, Vec2, WallFace};
+
+/// Mark on a wall face, such as a bullet hole.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Decal {
+    /// Texture coordinates of its center on the face, `v` going down from the top of the
+    /// wall.
+    pub u: f32,
+    pub v: f32,
+}
+
+impl Decal {
+    /// Width and height, in tiles.
+    pub const SIZE: f32 = 0.0625;
+    /// Decals kept per wall face, the oldest ones go first.
+    pub const MAX_PER_FACE: usize = 16;
+
+    /// Position inside the decal of texture coordinates `u`, `v` of its face, from 0 to
+    /// 1 on both axes, `None` outside.
+    pub fn local(&self, u: f32, v: f32) -> Option<Vec2> {
+        let local = |c: f32| (c / Self::SIZE) + 0.5;
+        let local = Vec2::new(local(u - self.u), local(v - self.v));
+        let inside = |c: f32| (0f32..1f32).contains(&c);
+        (inside(local.x) && inside(local.y)).then_some(local)
+    }
+}
+
+impl Game {
+    /// Adds a decal to a wall face, replacing its oldest one when it has too many.
+    pub fn add_decal(&mut self, tile: Vec2<i32>, face: WallFace, decal: Decal) {
+        let decals = self.decals.entry((tile, face)).or_default();
+        if decals.len() >= Decal::MAX_PER_FACE {
+            decals.pop_front();
+        }
+        decals.push_back(decal);
+    }
+
+    pub fn decals(&self, tile: Vec2<i32>, face: WallFace) -> Option<&VecDeque<Decal>> {
+        self.decals.get(&(tile, face))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Decal;
+    use crate::{Game, PixelBuffer, Player, TicCmd, Vec2, Wall, WallFace, WallKind};
+
+    #[test]
+    fn shots_leave_bounded_decals() {
+        // facing east, a wall half a tile ahead
+        let mut game = Game::new()
+            .with_player_position(Vec2::new(2.5, 0.5))
+            .with_player_direction(Vec2::new(1.0, 0.0))
+            .with_walls(&[Wall::new(WallKind::Basic, Vec2::new(3, 0))])
+            .with_minimap(None);
+        let hit = game
+            .shoot_ray(game.player.position, game.player.direction)
+            .unwrap();
+        assert_eq!((hit.tile, hit.face), (Vec2::new(3, 0), WallFace::West));
+        assert!((hit.u - 0.5).abs() < 1e-4 && (hit.v - 0.5).abs() < 1e-4);
+
+        let fire = TicCmd::new(0, 0, 0, TicCmd::FIRE);
+        for _ in 0..Player::REFIRE_TICS {
+            game.tick(&fire);
+        }
+        let decals = game.decals(hit.tile, hit.face).unwrap();
+        assert_eq!(decals.len(), 1);
+        let mut buf = PixelBuffer::new(40, 30);
+        game.render_world(&mut buf);
+        assert_eq!(buf.get(20, 15), Some(&game.view_style.decal));
+        assert_eq!(buf.get(20, 5), Some(&game.view_style.wall));
+
+        for _ in 0..Decal::MAX_PER_FACE * 2 {
+            game.fire();
+        }
+        let decals = game.decals(hit.tile, hit.face).unwrap();
+        assert_eq!(decals.len(), Decal::MAX_PER_FACE);
+    }
+}
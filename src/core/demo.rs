@@ -0,0 +1,243 @@
+use super::{Difficulty, Error, Game, Persist, SaveReader, SaveWriter, TicCmd};
+
+/// Leading bytes of every demo.
+pub const DEMO_MAGIC: [u8; 4] = *b"WDEM";
+/// Current demo format version, bump it whenever the layout changes.
+pub const DEMO_VERSION: u16 = 3;
+
+impl Persist for TicCmd {
+    fn save(&self, w: &mut SaveWriter) {
+        w.u8(self.forward as u8);
+        w.u8(self.strafe as u8);
+        w.u16(self.turn as u16);
+        w.u8(self.buttons);
+    }
+
+    fn load(r: &mut SaveReader) -> crate::Result<Self> {
+        Ok(Self {
+            forward: r.u8()? as i8,
+            strafe: r.u8()? as i8,
+            turn: r.u16()? as i16,
+            buttons: r.u8()?,
+        })
+    }
+}
+
+/// Per-tic input stream of a session, replayed on top of the game it was recorded from.
+///
+/// The demo does not carry the level itself, only checksums of the game state when the
+/// recording started and ended, so a replay on a different starting state or a desync
+/// is reported instead of silently diverging. Replaying it, for instance to reproduce a
+/// bug report, needs the game in the recorded starting state: ship the demo along with a
+/// [`Game::save`] taken when the recording started.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Demo {
+    pub difficulty: Difficulty,
+    pub level: u16,
+    pub start_checksum: u32,
+    pub end_checksum: u32,
+    pub cmds: Vec<TicCmd>,
+}
+
+impl Demo {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut w = SaveWriter::new();
+        w.bytes(&DEMO_MAGIC);
+        w.u16(DEMO_VERSION);
+        self.difficulty.save(&mut w);
+        w.u16(self.level);
+        w.u32(self.start_checksum);
+        w.u32(self.end_checksum);
+        w.list(&self.cmds);
+        w.into_bytes()
+    }
+
+    pub fn from_bytes(data: &[u8]) -> crate::Result<Self> {
+        let mut header = SaveReader::new(data, 0);
+        if header.bytes(DEMO_MAGIC.len())? != DEMO_MAGIC {
+            return Err(Error::InvalidDemo("not a demo".to_string()));
+        }
+        let version = header.u16()?;
+        if version == 0 || version > DEMO_VERSION {
+            return Err(Error::UnsupportedVersion {
+                found: version,
+                supported: DEMO_VERSION,
+            });
+        }
+        let mut r = SaveReader::new(&data[data.len() - header.remaining()..], version);
+        let difficulty = Difficulty::load(&mut r)?;
+        if version == 2 {
+            // generator seed, never used by the replay
+            r.u64()?;
+        }
+        Ok(Self {
+            difficulty,
+            level: r.u16()?,
+            start_checksum: r.u32()?,
+            end_checksum: r.u32()?,
+            cmds: r.list()?,
+        })
+    }
+
+    /// Length of the demo in tics.
+    pub fn len(&self) -> usize {
+        self.cmds.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cmds.is_empty()
+    }
+}
+
+/// Records the commands fed to a game, starting from its current state.
+#[derive(Debug, Clone)]
+pub struct DemoRecorder {
+    demo: Demo,
+}
+
+impl DemoRecorder {
+    pub fn start(game: &Game) -> Self {
+        Self {
+            demo: Demo {
+                difficulty: game.difficulty,
+                level: game.progress.level,
+                start_checksum: game.checksum(),
+                end_checksum: 0,
+                cmds: vec![],
+            },
+        }
+    }
+
+    /// Runs a tic of `game` with `cmd`, remembering the command.
+    pub fn tick(&mut self, game: &mut Game, cmd: TicCmd) {
+        game.tick(&cmd);
+        self.demo.cmds.push(cmd);
+    }
+
+    pub fn finish(mut self, game: &Game) -> Demo {
+        self.demo.end_checksum = game.checksum();
+        self.demo
+    }
+}
+
+/// Feeds a demo back to a game, one tic at a time.
+#[derive(Debug, Clone)]
+pub struct DemoPlayer {
+    demo: Demo,
+    tic: usize,
+}
+
+impl DemoPlayer {
+    /// Fails when `game` is not in the state the demo was recorded from.
+    pub fn new(demo: Demo, game: &Game) -> crate::Result<Self> {
+        let found = game.checksum();
+        if found != demo.start_checksum {
+            return Err(Error::DemoDesync {
+                tic: 0,
+                expected: demo.start_checksum,
+                found,
+            });
+        }
+        Ok(Self { demo, tic: 0 })
+    }
+
+    pub fn demo(&self) -> &Demo {
+        &self.demo
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.tic >= self.demo.cmds.len()
+    }
+
+    /// Plays the next tic, returns false once the demo is over.
+    pub fn tick(&mut self, game: &mut Game) -> bool {
+        match self.demo.cmds.get(self.tic) {
+            Some(cmd) => {
+                game.tick(cmd);
+                self.tic += 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Plays the remaining tics and checks the game ended up in the recorded state.
+    pub fn play(mut self, game: &mut Game) -> crate::Result<()> {
+        while self.tick(game) {}
+        self.finish(game)
+    }
+
+    /// Checks the game is in the state recorded at the end of the demo.
+    pub fn finish(&self, game: &Game) -> crate::Result<()> {
+        let found = game.checksum();
+        if found != self.demo.end_checksum {
+            return Err(Error::DemoDesync {
+                tic: self.tic,
+                expected: self.demo.end_checksum,
+                found,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Demo, DemoPlayer, DemoRecorder, Error, Game, TicCmd, Vec2, Wall, WallKind};
+
+    fn game() -> Game {
+        let mut walls = vec![];
+        for y in -3..=3 {
+            for x in -3..=3 {
+                if x == -3 || x == 3 || y == -3 || y == 3 {
+                    walls.push(Wall::new(WallKind::Basic, Vec2::new(x, y)))
+                }
+            }
+        }
+        Game::new().with_seed(7).with_walls(&walls)
+    }
+
+    fn record() -> Demo {
+        let mut game = game();
+        let mut rec = DemoRecorder::start(&game);
+        for i in 0..300 {
+            let cmd = TicCmd::new(i8::MAX, (i % 7) as i8 - 3, (i % 11) as i16 - 4, TicCmd::RUN);
+            rec.tick(&mut game, cmd);
+        }
+        rec.finish(&game)
+    }
+
+    #[test]
+    fn playback_is_identical() {
+        let demo = Demo::from_bytes(&record().to_bytes()).unwrap();
+        assert_eq!(demo.len(), 300);
+        let mut game = game();
+        DemoPlayer::new(demo, &game)
+            .unwrap()
+            .play(&mut game)
+            .unwrap();
+        assert_eq!(game.progress.tics, 300);
+    }
+
+    #[test]
+    fn desync_is_detected() {
+        let mut demo = record();
+        demo.cmds[10].turn += 1;
+        let mut game = game();
+        assert!(matches!(
+            DemoPlayer::new(demo.clone(), &game)
+                .unwrap()
+                .play(&mut game),
+            Err(Error::DemoDesync { tic: 300, .. })
+        ));
+
+        // tic by tic, as live playback does
+        let mut game = self::game();
+        let mut player = DemoPlayer::new(demo, &game).unwrap();
+        while player.tick(&mut game) {}
+        assert!(matches!(
+            player.finish(&game),
+            Err(Error::DemoDesync { tic: 300, .. })
+        ));
+    }
+}
@@ -0,0 +1,77 @@
+/// Skill level, mirrors the four levels of the original game.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Difficulty {
+    /// "Can I play, Daddy?"
+    Baby,
+    /// "Don't hurt me."
+    Easy,
+    /// "Bring 'em on!"
+    #[default]
+    Medium,
+    /// "I am Death incarnate!"
+    Hard,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 4] = [Self::Baby, Self::Easy, Self::Medium, Self::Hard];
+
+    pub fn from_index(index: u8) -> Option<Self> {
+        Self::ALL.get(index as usize).copied()
+    }
+
+    pub fn index(&self) -> u8 {
+        *self as u8
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Baby => "Can I play, Daddy?",
+            Self::Easy => "Don't hurt me.",
+            Self::Medium => "Bring 'em on!",
+            Self::Hard => "I am Death incarnate!",
+        }
+    }
+
+    /// Factor applied to the damage enemies deal to the player.
+    pub fn damage_scale(&self) -> f32 {
+        match self {
+            Self::Baby => 0.25,
+            Self::Easy => 0.5,
+            Self::Medium => 1.0,
+            Self::Hard => 1.25,
+        }
+    }
+
+    pub fn scale_damage(&self, points: i32) -> i32 {
+        (points as f32 * self.damage_scale()).round() as i32
+    }
+
+    /// Tics (1/70th of a second) an enemy waits after spotting the player before it reacts.
+    pub fn reaction_tics(&self) -> u16 {
+        match self {
+            Self::Baby => 40,
+            Self::Easy => 28,
+            Self::Medium => 18,
+            Self::Hard => 10,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Difficulty;
+    use crate::{Game, Player};
+
+    #[test]
+    fn damage_scales_with_difficulty() {
+        for (difficulty, taken) in Difficulty::ALL.into_iter().zip([5, 10, 20, 25]) {
+            assert_eq!(difficulty.scale_damage(20), taken);
+            let mut game = Game::new().with_difficulty(difficulty);
+            assert_eq!(game.hurt_player(20), taken);
+            assert_eq!(game.player.health, Player::MAX_HEALTH - taken);
+            // never more than the health left
+            assert_eq!(game.hurt_player(1000), Player::MAX_HEALTH - taken);
+            assert_eq!(game.player.health, 0);
+        }
+    }
+}
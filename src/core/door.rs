@@ -0,0 +1,39 @@
+use super::Vec2;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DoorLock {
+    #[default]
+    None,
+    Gold,
+    Silver,
+    Elevator,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Door {
+    pub position: Vec2<i32>,
+    /// Vertical doors slide along the y axis and are passed through east-west.
+    pub vertical: bool,
+    pub lock: DoorLock,
+    /// Opening amount, from 0 (closed) to 1 (fully open).
+    pub open: f32,
+}
+
+impl Door {
+    pub fn new(position: Vec2<i32>, vertical: bool, lock: DoorLock) -> Self {
+        Self {
+            position,
+            vertical,
+            lock,
+            open: 0f32,
+        }
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.open <= 0f32
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open >= 1f32
+    }
+}
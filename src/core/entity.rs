@@ -0,0 +1,104 @@
+use super::{Difficulty, Vec2};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EntityKind {
+    Guard,
+    Officer,
+    SS,
+    Dog,
+    Mutant,
+    Food,
+    FirstAid,
+    Clip,
+    GoldKey,
+    SilverKey,
+    Cross,
+    Chalice,
+    Chest,
+    Crown,
+    ExtraLife,
+    Decoration { sprite: u16, blocking: bool },
+}
+
+impl EntityKind {
+    pub fn is_enemy(&self) -> bool {
+        matches!(
+            self,
+            Self::Guard | Self::Officer | Self::SS | Self::Dog | Self::Mutant
+        )
+    }
+
+    pub fn is_treasure(&self) -> bool {
+        matches!(
+            self,
+            Self::Cross | Self::Chalice | Self::Chest | Self::Crown
+        )
+    }
+
+    pub fn is_pickup(&self) -> bool {
+        !self.is_enemy() && !matches!(self, Self::Decoration { .. })
+    }
+
+    /// Starting hit points, using the original per-difficulty table.
+    pub fn hit_points(&self, difficulty: Difficulty) -> i32 {
+        match self {
+            Self::Guard => 25,
+            Self::Officer => 50,
+            Self::SS => 100,
+            Self::Dog => 1,
+            Self::Mutant => [45, 55, 55, 65][difficulty.index() as usize],
+            _ => 0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Entity {
+    pub kind: EntityKind,
+    pub position: Vec2,
+    pub direction: Vec2,
+    pub health: i32,
+    pub patrolling: bool,
+    /// Tics left before the entity reacts once it noticed the player.
+    pub reaction_tics: u16,
+}
+
+impl Entity {
+    /// Half size of the collision box of actors, in tiles.
+    pub const RADIUS: f32 = 0.25;
+
+    pub fn new(kind: EntityKind, position: Vec2) -> Self {
+        Self {
+            kind,
+            position,
+            direction: Vec2::new(0f32, 1f32),
+            health: 0,
+            patrolling: false,
+            reaction_tics: 0,
+        }
+    }
+
+    /// Spawns the entity, applying the difficulty dependent stats.
+    pub fn spawn(kind: EntityKind, position: Vec2, difficulty: Difficulty) -> Self {
+        let mut ret = Self::new(kind, position);
+        ret.health = kind.hit_points(difficulty);
+        if kind.is_enemy() {
+            ret.reaction_tics = difficulty.reaction_tics();
+        }
+        ret
+    }
+
+    pub fn with_direction(mut self, dir: Vec2) -> Self {
+        self.direction = dir;
+        self
+    }
+
+    pub fn with_patrolling(mut self, patrolling: bool) -> Self {
+        self.patrolling = patrolling;
+        self
+    }
+
+    pub fn is_alive(&self) -> bool {
+        !self.kind.is_enemy() || self.health > 0
+    }
+}
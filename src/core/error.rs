@@ -5,12 +5,27 @@ pub enum Error {
     IO(String),
     OutOfBounds {
         value: i32,
-        range: Range<i32>
+        range: Range<i32>,
     },
     OutOfBounds2D {
         point: (f32, f32),
         range: (Range<f32>, Range<f32>),
     },
+    InvalidMap(String),
+    InvalidSave(String),
+    InvalidDemo(String),
+    InvalidMusic(String),
+    InvalidImage(String),
+    InvalidAudio(String),
+    DemoDesync {
+        tic: usize,
+        expected: u32,
+        found: u32,
+    },
+    UnsupportedVersion {
+        found: u16,
+        supported: u16,
+    },
 }
 
 impl Error {
@@ -19,17 +34,43 @@ impl Error {
             Self::IO(..) => "I/O",
             Self::OutOfBounds2D { .. } => "OutOfBounds2D",
             Self::OutOfBounds { .. } => "OutOfBounds",
+            Self::InvalidMap(..) => "InvalidMap",
+            Self::InvalidSave(..) => "InvalidSave",
+            Self::InvalidDemo(..) => "InvalidDemo",
+            Self::InvalidMusic(..) => "InvalidMusic",
+            Self::InvalidImage(..) => "InvalidImage",
+            Self::InvalidAudio(..) => "InvalidAudio",
+            Self::DemoDesync { .. } => "DemoDesync",
+            Self::UnsupportedVersion { .. } => "UnsupportedVersion",
         }
     }
 
     pub fn message(&self) -> Option<String> {
         match self {
             Self::IO(m) => Some(m.clone()),
-            Self::OutOfBounds { value, range } => Some(format!("value {} out of bounds ({}..{})", value, range.start, range.end)),
+            Self::OutOfBounds { value, range } => Some(format!(
+                "value {} out of bounds ({}..{})",
+                value, range.start, range.end
+            )),
             Self::OutOfBounds2D { point, range } => Some(format!(
                 "point ({}, {}) is out of bounds ({}..{}, {}..{})",
                 point.0, point.1, range.0.start, range.0.end, range.1.start, range.1.end
             )),
+            Self::InvalidMap(m) | Self::InvalidSave(m) | Self::InvalidDemo(m) => Some(m.clone()),
+            Self::InvalidMusic(m) | Self::InvalidImage(m) => Some(m.clone()),
+            Self::InvalidAudio(m) => Some(m.clone()),
+            Self::DemoDesync {
+                tic,
+                expected,
+                found,
+            } => Some(format!(
+                "state checksum {:08x} at tic {} differs from the recorded {:08x}",
+                found, tic, expected
+            )),
+            Self::UnsupportedVersion { found, supported } => Some(format!(
+                "version {} is not supported, expected at most {}",
+                found, supported
+            )),
         }
     }
 }
@@ -0,0 +1,113 @@
+use super::Color;
+
+/// How fog thickens with the distance.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum FogFalloff {
+    /// No fog up to `start`, full fog from `end`.
+    #[default]
+    Linear,
+    /// `1 - e^(-density * d)`, past `start`.
+    Exponential,
+    /// `1 - e^(-(density * d)²)`, past `start`: clear nearby, then closing in quickly.
+    ExponentialSquared,
+}
+
+/// Distance shading, blending what is drawn towards `color` the further away it is.
+/// Black fog darkens the view like a light falloff.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Fog {
+    pub color: Color,
+    pub falloff: FogFalloff,
+    /// Distance the fog starts at, in tiles.
+    pub start: f32,
+    /// Distance the linear fog is opaque at, in tiles.
+    pub end: f32,
+    /// Thickness of the exponential fogs.
+    pub density: f32,
+    /// Highest fog amount, below 1 so far walls stay slightly visible.
+    pub max: f32,
+}
+
+impl Default for Fog {
+    fn default() -> Self {
+        Self {
+            color: Color::black(),
+            falloff: FogFalloff::Linear,
+            start: 2f32,
+            end: 16f32,
+            density: 0.15,
+            max: 0.9,
+        }
+    }
+}
+
+impl Fog {
+    pub fn new(color: Color, falloff: FogFalloff) -> Self {
+        Self {
+            color,
+            falloff,
+            ..Default::default()
+        }
+    }
+
+    /// Fog amount at `distance`, from 0 (clear) to `max`.
+    pub fn factor(&self, distance: f32) -> f32 {
+        let d = (distance - self.start).max(0f32);
+        let f = match self.falloff {
+            FogFalloff::Linear => d / (self.end - self.start).max(f32::EPSILON),
+            FogFalloff::Exponential => 1f32 - (-self.density * d).exp(),
+            FogFalloff::ExponentialSquared => 1f32 - (-(self.density * d).powi(2)).exp(),
+        };
+        f.clamp(0f32, self.max.clamp(0f32, 1f32))
+    }
+
+    pub fn apply(&self, color: Color, distance: f32) -> Color {
+        self.apply_factor(color, self.factor(distance))
+    }
+
+    /// Applies a factor from [`Fog::factor`], for spans sharing the same distance.
+    pub fn apply_factor(&self, color: Color, factor: f32) -> Color {
+        if factor <= 0f32 {
+            return color;
+        }
+        let alpha = color.a;
+        Color {
+            a: alpha,
+            ..color.lerp(self.color, factor)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Fog, FogFalloff};
+    use crate::Color;
+
+    #[test]
+    fn falloff_curves() {
+        let fog = Fog {
+            start: 2.0,
+            end: 12.0,
+            max: 1.0,
+            ..Fog::new(Color::black(), FogFalloff::Linear)
+        };
+        assert_eq!(fog.factor(1.0), 0.0);
+        assert!((fog.factor(7.0) - 0.5).abs() < 1e-6);
+        assert_eq!(fog.factor(100.0), 1.0);
+        assert_eq!(
+            fog.apply(Color::white(), 7.0),
+            Color::new(128, 128, 128, 255)
+        );
+
+        let exp = Fog {
+            falloff: FogFalloff::Exponential,
+            ..fog
+        };
+        let exp2 = Fog {
+            falloff: FogFalloff::ExponentialSquared,
+            ..fog
+        };
+        assert!(exp.factor(3.0) > exp2.factor(3.0));
+        assert!(exp.factor(4.0) > exp.factor(3.0));
+    }
+}
@@ -1,61 +1,313 @@
 use std::{
-    fmt::{Debug, Display},
-    ops::Add,
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    fmt::Debug,
     sync::{Arc, Mutex},
 };
 
 use lazy_static::lazy_static;
 
-use crate::{console_log, Color, Rect};
+use crate::Color;
 
-use super::{PixelBuffer, Ray, ToPrimitive, Vec2};
+use super::{
+    intersect_segment, Animation, Automap, Camera, ColorMap, Dda, Decal, Difficulty, Door, Emitter,
+    Entity, Flats, Fog, IndexedBuffer, Map, MinimapStyle, Palette, PaletteEffects, ParticleStyle,
+    ParticleSystem, PixelBuffer, RenderScale, Rng, SegmentHit, Teleporter, TextureId, TicCmd,
+    TileGrid, ToPrimitive, Vec2, ViewStyle, WallFace, WallHit, MAX_VIEW_DISTANCE, TICS_PER_SECOND,
+};
 
 #[derive(Debug, Clone, Copy, Default)]
 pub struct Player {
     pub position: Vec2,
     pub direction: Vec2,
+    pub health: i32,
+    /// Tics before the weapon can fire again.
+    pub refire_tics: u16,
 }
 
 impl Player {
+    pub const MAX_HEALTH: i32 = 100;
+    /// Half size of the player's collision box, in tiles.
+    pub const RADIUS: f32 = 0.34375;
+    /// Walking speed in tiles per second, doubled when running.
+    pub const SPEED: f32 = 3.0;
+    /// Horizontal field of view at a 4:3 aspect ratio, in degrees.
+    pub const FOV: f32 = 66.0;
+    /// Height of the eyes above the floor, in tiles.
+    pub const EYE_HEIGHT: f32 = 0.5;
+    /// Highest floor step the player can walk up, in tiles.
+    pub const MAX_STEP: f32 = 0.25;
+    /// Tics between two shots while holding fire.
+    pub const REFIRE_TICS: u16 = 10;
+
     pub fn new(position: Vec2, direction: Vec2) -> Self {
         Self {
             position,
             direction,
+            health: Self::MAX_HEALTH,
+            refire_tics: 0,
         }
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
 pub enum WallKind {
+    /// Fills its whole tile.
     Basic,
+    /// Wall of no thickness across its tile, along the y axis when `vertical`, `offset`
+    /// tiles from the west (or north) edge: 0 for an edge, 0.5 for the middle.
+    Thin { vertical: bool, offset: f32 },
+    /// Wall of no thickness from the north-west corner of its tile to the south-east
+    /// one, or from the north-east corner to the south-west one when `flipped`.
+    Diagonal { flipped: bool },
+    /// Thin wall like [`WallKind::Thin`] whose texture has transparent parts, for cage
+    /// bars, fences and windows: what is behind shows through.
+    SeeThrough { vertical: bool, offset: f32 },
 }
 
 #[derive(Clone, Copy, Debug)]
 pub struct Wall {
     pub kind: WallKind,
     pub position: Vec2<i32>,
+    /// Height above the floor of its tile, 1 being the height of the ceiling.
+    pub height: f32,
+    /// Texture of its sides, drawn in the view style color when `None`.
+    pub texture: Option<TextureId>,
+    pub blocks_movement: bool,
+    /// Whether it stops the exploration rays, hiding what is behind from the automap.
+    /// See-through walls don't by default.
+    pub blocks_sight: bool,
 }
 
 impl Wall {
     pub fn new(kind: WallKind, position: Vec2<i32>) -> Self {
-        Self { kind, position }
+        Self {
+            kind,
+            position,
+            height: 1f32,
+            texture: None,
+            blocks_movement: true,
+            blocks_sight: !matches!(kind, WallKind::SeeThrough { .. }),
+        }
+    }
+
+    pub fn with_height(mut self, height: f32) -> Self {
+        self.height = height;
+        self
+    }
+
+    pub fn with_texture(mut self, texture: TextureId) -> Self {
+        self.texture = Some(texture);
+        self
+    }
+
+    pub fn with_blocks_movement(mut self, blocks: bool) -> Self {
+        self.blocks_movement = blocks;
+        self
+    }
+
+    pub fn with_blocks_sight(mut self, blocks: bool) -> Self {
+        self.blocks_sight = blocks;
+        self
+    }
+
+    /// Ends of a thin or diagonal wall in world space, `None` for basic walls.
+    pub fn segment(&self) -> Option<(Vec2, Vec2)> {
+        let tile = self.position.cast::<f32>();
+        let (a, b) = match self.kind {
+            WallKind::Basic => return None,
+            WallKind::Thin { vertical, offset } | WallKind::SeeThrough { vertical, offset } => {
+                match vertical {
+                    true => (Vec2::new(offset, 0f32), Vec2::new(offset, 1f32)),
+                    false => (Vec2::new(0f32, offset), Vec2::new(1f32, offset)),
+                }
+            }
+            WallKind::Diagonal { flipped: false } => (Vec2::new(0f32, 0f32), Vec2::new(1f32, 1f32)),
+            WallKind::Diagonal { flipped: true } => (Vec2::new(1f32, 0f32), Vec2::new(0f32, 1f32)),
+        };
+        Some((tile + a, tile + b))
+    }
+
+    /// Whether a box of half size `radius` centered on `pos` overlaps the wall.
+    pub fn overlaps(&self, pos: Vec2, radius: f32) -> bool {
+        let separated =
+            |min: f32, max: f32, center: f32| max <= center - radius || min >= center + radius;
+        let Some((a, b)) = self.segment() else {
+            let tile = self.position.cast::<f32>();
+            return !separated(tile.x, tile.x + 1f32, pos.x)
+                && !separated(tile.y, tile.y + 1f32, pos.y);
+        };
+        // separating axes: x, y and the normal of the segment
+        let normal = Vec2::new(a.y - b.y, b.x - a.x);
+        !separated(a.x.min(b.x), a.x.max(b.x), pos.x)
+            && !separated(a.y.min(b.y), a.y.max(b.y), pos.y)
+            && (pos - a).dot(normal).abs() < radius * (normal.x.abs() + normal.y.abs())
     }
 }
 
+/// What occupies a tile, indices refer to `Game::walls` and `Game::doors`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Tile {
+    #[default]
+    Empty,
+    Wall(usize),
+    Door(usize),
+}
+
+/// Progress through the current level, shown on the intermission screen.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LevelProgress {
+    pub level: u16,
+    pub tics: u32,
+    pub kills: u16,
+    pub total_kills: u16,
+    pub treasures: u16,
+    pub total_treasures: u16,
+}
+
 #[derive(Debug, Clone)]
 pub struct Game {
     pub player: Player,
     pub walls: Vec<Wall>,
+    pub doors: Vec<Door>,
+    pub entities: Vec<Entity>,
+    pub difficulty: Difficulty,
+    pub progress: LevelProgress,
+    pub rng: Rng,
+    /// Minimap drawn over the view, `None` hides it.
+    pub minimap: Option<MinimapStyle>,
+    /// Tiles the player has seen so far.
+    pub explored: BTreeSet<Vec2<i32>>,
+    pub automap: Automap,
+    pub camera: Camera,
+    pub render_scale: RenderScale,
+    pub palette_effects: PaletteEffects,
+    /// Colors of the 256 color mode, frames then go through an [`IndexedBuffer`] and the
+    /// palette effects only shift the palette. `None` renders in true color.
+    pub indexed: Option<ColorMap>,
+    /// Distance shading of the 3D view, `None` for the flat lighting of the original.
+    pub fog: Option<Fog>,
+    pub view_style: ViewStyle,
+    /// Textures, referenced by their index.
+    pub textures: Vec<PixelBuffer>,
+    /// Animations played in place of textures.
+    pub animations: BTreeMap<TextureId, Animation>,
+    /// Textures of the decoration sprites, by sprite number.
+    pub sprite_textures: BTreeMap<u16, TextureId>,
+    /// Floor and ceiling textures per tile.
+    pub flats: BTreeMap<Vec2<i32>, Flats>,
+    /// Sky of the whole map, over every tile without a ceiling texture.
+    pub sky: Option<TextureId>,
+    /// Floor heights of the tiles not at 0.
+    pub floor_heights: BTreeMap<Vec2<i32>, f32>,
+    /// Bullet holes per wall face, oldest first. Not saved, loading a save clears them.
+    pub decals: BTreeMap<(Vec2<i32>, WallFace), VecDeque<Decal>>,
+    /// Texture of the decals, `None` draws them with the flat color of the
+    /// [`ViewStyle`].
+    pub decal_texture: Option<TextureId>,
+    pub particles: ParticleSystem,
+    /// Teleporters by tile.
+    pub teleporters: BTreeMap<Vec2<i32>, Teleporter>,
+    /// Wall faces with a portal, and the faces they are linked to.
+    pub portals: BTreeMap<(Vec2<i32>, WallFace), (Vec2<i32>, WallFace)>,
+    /// Size of the last render target given to [`Game::resize`].
+    output_size: Vec2<usize>,
+    /// Player tile, direction and total door opening of the last exploration.
+    explored_view: Option<(Vec2<i32>, Vec2, f32)>,
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Game {
+    /// Length of the fade to red when the player dies.
+    pub const DEATH_FADE_TICS: u32 = 35;
+
     pub fn new() -> Self {
         Self {
             player: Player::new(Vec2::new(0f32, 0f32), Vec2::new(0f32, -1f32)),
             walls: vec![],
+            doors: vec![],
+            entities: vec![],
+            difficulty: Difficulty::default(),
+            progress: LevelProgress::default(),
+            rng: Rng::default(),
+            minimap: Some(MinimapStyle::default()),
+            explored: BTreeSet::new(),
+            automap: Automap::default(),
+            camera: Camera::default(),
+            render_scale: RenderScale::default(),
+            palette_effects: PaletteEffects::new(),
+            indexed: None,
+            fog: None,
+            view_style: ViewStyle::default(),
+            textures: vec![],
+            animations: BTreeMap::new(),
+            sprite_textures: BTreeMap::new(),
+            flats: BTreeMap::new(),
+            sky: None,
+            floor_heights: BTreeMap::new(),
+            decals: BTreeMap::new(),
+            decal_texture: None,
+            particles: ParticleSystem::new(),
+            teleporters: BTreeMap::new(),
+            portals: BTreeMap::new(),
+            output_size: Vec2::new(0, 0),
+            explored_view: None,
         }
     }
 
+    pub fn with_minimap(mut self, style: Option<MinimapStyle>) -> Self {
+        self.minimap = style;
+        self
+    }
+
+    pub fn with_fog(mut self, fog: Option<Fog>) -> Self {
+        self.fog = fog;
+        self
+    }
+
+    pub fn with_camera(mut self, camera: Camera) -> Self {
+        self.camera = camera;
+        self
+    }
+
+    /// Adapts the projection to a new `width`x`height` render target.
+    pub fn resize(&mut self, width: usize, height: usize) {
+        self.output_size = Vec2::new(width, height);
+        let viewport = self.render_scale.viewport(self.output_size);
+        self.camera.set_viewport(viewport.x, viewport.y);
+    }
+
+    /// Changes the internal render resolution, see [`Game::render_scaled`].
+    pub fn set_render_scale(&mut self, scale: RenderScale) {
+        self.render_scale = scale;
+        self.resize(self.output_size.x, self.output_size.y);
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = Rng::with_mode(self.rng.mode(), seed);
+        self
+    }
+
+    pub fn with_rng(mut self, rng: Rng) -> Self {
+        self.rng = rng;
+        self
+    }
+
+    pub fn with_difficulty(mut self, difficulty: Difficulty) -> Self {
+        self.difficulty = difficulty;
+        self
+    }
+
+    /// Loads the map using the current difficulty, see [`Game::load_map`].
+    pub fn with_map(mut self, map: &Map) -> Self {
+        self.load_map(map);
+        self
+    }
+
     pub fn with_player(mut self, p: Player) -> Self {
         self.player = p;
         self
@@ -89,57 +341,270 @@ impl Game {
         &self.walls
     }
 
-    pub fn render(&self, dt: f32, buf: &mut PixelBuffer) {
-        buf.fill(Color::black());
-        // console_log!(
-        //     "Rendering game, player_pos = {}, player_dir = {}",
-        //     self.player.position,
-        //     self.player.direction
-        // );
-        self.render_minimap(buf);
+    pub fn doors(&self) -> &Vec<Door> {
+        &self.doors
     }
 
-    fn render_minimap(&self, buf: &mut PixelBuffer) {
-        const MINIMAP_SIZE: Vec2<usize> = Vec2::new(100, 100);
-        const MINIMAP_TILE_SIZE: Vec2<usize> = Vec2::new(5, 5);
-        const MINIMAP_TILES_COUNT: Vec2<f32> = Vec2::new(
-            (MINIMAP_SIZE.x as f32) / (MINIMAP_TILE_SIZE.x as f32),
-            (MINIMAP_SIZE.y as f32) / (MINIMAP_TILE_SIZE.y as f32),
-        );
-        const MINIMAP_BORDER_COLOR: Option<Color> = Some(Color::blue());
-        const MINIMAP_PLAYER_COLOR: Color = Color::red();
-        if let Some(border_color) = MINIMAP_BORDER_COLOR.as_ref() {
-            for y in 0..MINIMAP_SIZE.x {
-                for x in 0..MINIMAP_SIZE.y {
-                    if x == 0 || x == (MINIMAP_SIZE.x - 1) || y == 0 || y == (MINIMAP_SIZE.y - 1) {
-                        let _ = buf.put(x, y, *border_color);
-                    }
+    pub fn entities(&self) -> &Vec<Entity> {
+        &self.entities
+    }
+
+    pub fn rng(&mut self) -> &mut Rng {
+        &mut self.rng
+    }
+
+    pub fn difficulty(&self) -> Difficulty {
+        self.difficulty
+    }
+
+    /// Replaces walls and entities with the map content, only spawning the entities
+    /// matching the current difficulty.
+    pub fn load_map(&mut self, map: &Map) {
+        self.walls = map.walls();
+        self.doors = map.doors();
+        self.explored.clear();
+        self.explored_view = None;
+        self.decals.clear();
+        self.particles.clear();
+        self.entities = map.entities(self.difficulty);
+        self.progress = LevelProgress {
+            level: self.progress.level,
+            total_kills: self.entities.iter().filter(|e| e.kind.is_enemy()).count() as u16,
+            total_treasures: self
+                .entities
+                .iter()
+                .filter(|e| e.kind.is_treasure())
+                .count() as u16,
+            ..Default::default()
+        };
+        if let Some(start) = map.player_start() {
+            self.player.position = start.position;
+            self.player.direction = start.direction;
+        }
+    }
+
+    /// Advances the simulation by one tic. The outcome only depends on the current
+    /// state and `cmd`, so replaying the same commands reproduces the same game.
+    pub fn tick(&mut self, cmd: &TicCmd) {
+        if cmd.turn != 0 {
+            self.player.direction.rotate(cmd.turn_degrees());
+            self.player.direction.normalize();
+        }
+        let mut speed = Player::SPEED / TICS_PER_SECOND as f32 / i8::MAX as f32;
+        if cmd.pressed(TicCmd::RUN) {
+            speed *= 2.0;
+        }
+        let dir = self.player.direction;
+        let right = Vec2::new(-dir.y, dir.x);
+        let delta =
+            dir.scaled(cmd.forward as f32 * speed) + right.scaled(cmd.strafe as f32 * speed);
+        self.move_player(delta);
+        self.player.refire_tics = self.player.refire_tics.saturating_sub(1);
+        if cmd.pressed(TicCmd::FIRE) && self.player.refire_tics == 0 {
+            self.fire();
+        }
+        self.explore_if_moved();
+        let heights = &self.floor_heights;
+        self.particles
+            .tick(|tile| heights.get(&tile).copied().unwrap_or_default());
+        self.palette_effects.tick();
+        self.progress.tics += 1;
+    }
+
+    /// Index of the walls and doors, `None` when there are none.
+    pub fn tiles(&self) -> Option<TileGrid<Tile>> {
+        let positions = self.walls.iter().map(|w| w.position);
+        let positions = positions.chain(self.doors.iter().map(|d| d.position));
+        let mut grid = TileGrid::covering(positions, Tile::Empty)?;
+        for (i, wall) in self.walls.iter().enumerate() {
+            grid.set(wall.position, Tile::Wall(i));
+        }
+        for (i, door) in self.doors.iter().enumerate() {
+            grid.set(door.position, Tile::Door(i));
+        }
+        Some(grid)
+    }
+
+    /// Marks the tiles in the player's field of view as explored, rays stop at walls
+    /// blocking sight and closed doors rising above the eyes.
+    pub fn explore(&mut self) {
+        const RAYS: usize = 64;
+        const MAX_DISTANCE: f32 = 64.0;
+        let origin = self.player.position;
+        let fov = self.camera.horizontal_fov();
+        self.explored.insert(origin.floor::<i32>());
+        let Some(tiles) = self.tiles() else {
+            return;
+        };
+        let floors = self.floor_grid();
+        let eye = self.eye_height();
+        for i in 0..=RAYS {
+            let angle = fov * (i as f32 / RAYS as f32 - 0.5);
+            let dir = self.player.direction.rotated(angle);
+            for step in Dda::new(origin, dir, MAX_DISTANCE) {
+                if !tiles.contains(step.tile) {
+                    break;
+                }
+                self.explored.insert(step.tile);
+                if self.stops_sight(&tiles, floors.as_ref(), step.tile, origin, dir, eye) {
+                    break;
                 }
             }
         }
-        let world_bounds = Rect::new(
-            self.player.position - MINIMAP_TILES_COUNT * MINIMAP_TILE_SIZE.cast::<f32>() * Vec2::scalar(0.5f32),
-            self.player.position - MINIMAP_TILES_COUNT * MINIMAP_TILE_SIZE.cast::<f32>() * Vec2::scalar(0.5f32),
-        );
-        console_log!("minimap world bounds: {:?}")
-        let player_pos = Vec2::new(
-            (MINIMAP_SIZE.0 as f32 * 0.5f32) as usize,
-            (MINIMAP_SIZE.0 as f32 * 0.5f32) as usize,
+    }
+
+    /// Explores again only when the player changed tile or direction, or a door moved,
+    /// the rays are too costly to cast every tic.
+    fn explore_if_moved(&mut self) {
+        let view = (
+            self.player.position.floor::<i32>(),
+            self.player.direction,
+            self.doors.iter().map(|d| d.open).sum(),
         );
-        for y in player_pos.y - 1..player_pos.y + 1 {
-            for x in player_pos.x - 1..player_pos.x + 1 {
-                let _ = buf.put(x, y, MINIMAP_PLAYER_COLOR);
+        if self.explored_view != Some(view) {
+            self.explore();
+            self.explored_view = Some(view);
+        }
+    }
+
+    pub fn is_explored(&self, tile: Vec2<i32>) -> bool {
+        self.explored.contains(&tile)
+    }
+
+    /// Moves the player by `delta`, sliding along walls when blocked, through the portals
+    /// and teleporters it walks into.
+    pub fn move_player(&mut self, delta: Vec2) {
+        let pos = self.player.position;
+        if let Some(portal) = self.portal_crossed(pos, pos + delta) {
+            // a portal the player doesn't fit through stops it like a wall
+            let exit = portal.point(pos + delta);
+            if !self.is_player_blocked(exit) && self.can_step(exit, Player::RADIUS) {
+                self.player.position = exit;
+                self.player.direction = portal.direction(self.player.direction);
             }
+            return;
         }
-        buf.line(
-            player_pos.cast::<isize>(),
-            player_pos.cast::<isize>() + self.player.direction.scaled(20.0).cast::<isize>(),
-            Color::green(),
-        );
+        for candidate in [
+            pos + delta,
+            pos + Vec2::new(delta.x, 0f32),
+            pos + Vec2::new(0f32, delta.y),
+        ] {
+            if !self.is_player_blocked(candidate) && self.can_step(candidate, Player::RADIUS) {
+                self.player.position = candidate;
+                self.teleport_player(pos.floor::<i32>());
+                return;
+            }
+        }
+    }
+
+    /// Whether a box of half size `radius` centered on `pos` overlaps a wall or a closed door.
+    pub fn is_blocked(&self, pos: Vec2, radius: f32) -> bool {
+        self.is_blocked_except(pos, radius, |_| false)
+    }
+
+    /// Same as [`Game::is_blocked`], ignoring the walls of the tiles `ignored` accepts.
+    pub fn is_blocked_except(
+        &self,
+        pos: Vec2,
+        radius: f32,
+        ignored: impl Fn(Vec2<i32>) -> bool,
+    ) -> bool {
+        let start = (pos - Vec2::scalar(radius)).floor::<i32>();
+        let end = (pos + Vec2::scalar(radius)).floor::<i32>();
+        (start.y..=end.y).any(|y| {
+            (start.x..=end.x).any(|x| {
+                let tile = Vec2::new(x, y);
+                self.wall_at(tile)
+                    .is_some_and(|w| w.blocks_movement && w.overlaps(pos, radius) && !ignored(tile))
+                    || self.door_at(tile).is_some_and(|d| !d.is_open())
+            })
+        })
+    }
+
+    /// Applies enemy damage to the player, scaled by the difficulty.
+    /// Returns the damage actually taken.
+    pub fn hurt_player(&mut self, points: i32) -> i32 {
+        let points = self.difficulty.scale_damage(points).min(self.player.health);
+        self.player.health -= points;
+        self.palette_effects.damage(points.max(0) as u32);
+        if points > 0 && self.player.health <= 0 {
+            self.palette_effects
+                .fade_out(Color::red(), Self::DEATH_FADE_TICS);
+        }
+        points
+    }
+
+    pub fn render(&self, _dt: f32, buf: &mut PixelBuffer) {
+        if self.automap.visible {
+            self.render_automap(buf);
+            return;
+        }
+        self.render_world(buf);
+        // console_log!(
+        //     "Rendering game, player_pos = {}, player_dir = {}",
+        //     self.player.position,
+        //     self.player.direction
+        // );
+        if let Some(style) = self.minimap.as_ref() {
+            self.render_minimap(buf, style);
+        }
+        // the indexed path shifts the palette instead, see `Game::present_indexed`
+        if self.indexed.is_none() {
+            self.palette_effects.apply_buffer(buf);
+        }
+    }
+
+    /// Converts an indexed frame to RGBA into `out`, applying the palette effects to
+    /// `palette` only.
+    pub fn present_indexed(&self, frame: &IndexedBuffer, palette: &Palette, out: &mut PixelBuffer) {
+        frame.present(&self.palette_effects.apply(palette), out);
+    }
+
+    pub fn door_at(&self, pos: Vec2<i32>) -> Option<&Door> {
+        self.doors.iter().find(|d| d.position == pos)
     }
 
     pub fn wall_at<T: Copy + ToPrimitive>(&self, pos: Vec2<T>) -> Option<&Wall> {
-        self.walls.iter().find(|w| w.position == pos.cast::<i32>())
+        let tile = pos.floor::<i32>();
+        self.walls.iter().find(|w| w.position == tile)
+    }
+
+    /// Whether what fills `tile` hides what is behind from eyes at height `eye`, along
+    /// the ray from `origin` along `dir`.
+    fn stops_sight(
+        &self,
+        tiles: &TileGrid<Tile>,
+        floors: Option<&TileGrid<f32>>,
+        tile: Vec2<i32>,
+        origin: Vec2,
+        dir: Vec2,
+        eye: f32,
+    ) -> bool {
+        match tiles.get(tile) {
+            Some(Tile::Wall(w)) if !self.walls[*w].blocks_sight => false,
+            Some(Tile::Wall(w)) if self.walls[*w].segment().is_some() => {
+                let floor = floors.and_then(|f| f.get(tile)).copied();
+                self.segment_hit(tiles, tile, origin, dir)
+                    .is_some_and(|(wall, _)| floor.unwrap_or_default() + wall.height > eye)
+            }
+            _ => self.column_top(tiles, floors, tile) > eye,
+        }
+    }
+
+    /// Thin, diagonal or see-through wall of `tile` hit by the ray from `origin` along `dir`.
+    pub fn segment_hit(
+        &self,
+        tiles: &TileGrid<Tile>,
+        tile: Vec2<i32>,
+        origin: Vec2,
+        dir: Vec2,
+    ) -> Option<(&Wall, SegmentHit)> {
+        let Some(Tile::Wall(w)) = tiles.get(tile) else {
+            return None;
+        };
+        let wall = &self.walls[*w];
+        let (a, b) = wall.segment()?;
+        intersect_segment(origin, dir, a, b).map(|hit| (wall, hit))
     }
 
     pub fn ascii_walls(&self) -> Vec<String> {
@@ -167,18 +632,68 @@ impl Game {
         repr
     }
 
-    pub fn shoot_ray<'a>(
-        &'a self,
-        start: Vec2<f32>,
-        dir: Vec2<f32>,
-    ) -> (Ray<f32>, Vec<(Vec2<f32>, &'a Wall)>) {
-        let mut ray = Ray::new(start, dir, 100f32, 0.5).expect("failed to create ray");
-        let intersections = ray
-            .samples()
-            .iter()
-            .filter_map(|sample| self.wall_at(*sample).map(|w| (*sample, w)))
-            .collect::<Vec<_>>();
-        (ray, intersections)
+    /// First wall hit by a hitscan ray from `start` along `dir` at eye height, `None` when
+    /// it stops at a door or leaves the map.
+    pub fn shoot_ray(&self, start: Vec2, dir: Vec2) -> Option<WallHit> {
+        let tiles = self.tiles()?;
+        let floors = self.floor_grid();
+        let mut tile = start.floor::<i32>();
+        let eye = self.floor_height(tile) + Player::EYE_HEIGHT;
+        let dir = dir.normalized();
+        let mut steps = Dda::new(start, dir, MAX_VIEW_DISTANCE);
+        loop {
+            if let Some((wall, hit)) = self.segment_hit(&tiles, tile, start, dir) {
+                let top = self.floor_height(tile) + wall.height;
+                if top > eye {
+                    let (a, b) = wall.segment().unwrap_or_default();
+                    let face = WallFace::of_segment(a, b, dir);
+                    let position = start + dir.scaled(hit.distance);
+                    return Some(WallHit {
+                        tile,
+                        face,
+                        position,
+                        distance: hit.distance,
+                        u: face.u(position, hit.u),
+                        v: top - eye,
+                    });
+                }
+            }
+            let step = steps.next()?;
+            tile = step.tile;
+            if !tiles.contains(tile) {
+                return None;
+            }
+            let top = self.column_top(&tiles, floors.as_ref(), tile);
+            if top > eye {
+                if let Some(Tile::Door(_)) = tiles.get(tile) {
+                    return None;
+                }
+                let face = WallFace::entered(step.side, dir);
+                let position = start + dir.scaled(step.distance);
+                return Some(WallHit {
+                    tile,
+                    face,
+                    position,
+                    distance: step.distance,
+                    u: face.u(position, 0f32),
+                    v: top - eye,
+                });
+            }
+        }
+    }
+
+    /// Fires the player's weapon, leaving a bullet hole and sparks on the wall it hits.
+    pub fn fire(&mut self) {
+        self.player.refire_tics = Player::REFIRE_TICS;
+        let dir = self.player.direction.normalized();
+        if let Some(hit) = self.shoot_ray(self.player.position, dir) {
+            self.add_decal(hit.tile, hit.face, Decal { u: hit.u, v: hit.v });
+            // just in front of the wall, shots fly at eye height
+            let position = hit.position - dir.scaled(0.05);
+            let sparks =
+                Emitter::new(position, self.eye_height(), ParticleStyle::SPARKS).with_burst(6);
+            self.particles.add_emitter(sparks);
+        }
     }
 }
 
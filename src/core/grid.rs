@@ -0,0 +1,93 @@
+use super::Vec2;
+
+/// Dense per-tile storage over a rectangular area of the world, for lookups that
+/// would be too slow scanning `Game::walls`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileGrid<T: Clone> {
+    origin: Vec2<i32>,
+    width: usize,
+    height: usize,
+    cells: Vec<T>,
+}
+
+impl<T: Clone> TileGrid<T> {
+    pub fn new(origin: Vec2<i32>, width: usize, height: usize, fill: T) -> Self {
+        Self {
+            origin,
+            width,
+            height,
+            cells: vec![fill; width * height],
+        }
+    }
+
+    /// Smallest grid covering every position, `None` when there are none.
+    pub fn covering(positions: impl Iterator<Item = Vec2<i32>>, fill: T) -> Option<Self> {
+        let mut bounds: Option<(Vec2<i32>, Vec2<i32>)> = None;
+        for p in positions {
+            bounds = Some(match bounds {
+                None => (p, p),
+                Some((min, max)) => (
+                    Vec2::new(min.x.min(p.x), min.y.min(p.y)),
+                    Vec2::new(max.x.max(p.x), max.y.max(p.y)),
+                ),
+            });
+        }
+        let (min, max) = bounds?;
+        Some(Self::new(
+            min,
+            (max.x - min.x + 1) as usize,
+            (max.y - min.y + 1) as usize,
+            fill,
+        ))
+    }
+
+    pub fn origin(&self) -> Vec2<i32> {
+        self.origin
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    fn index(&self, pos: Vec2<i32>) -> Option<usize> {
+        let rel = pos - self.origin;
+        if rel.x < 0 || rel.y < 0 || rel.x as usize >= self.width || rel.y as usize >= self.height {
+            return None;
+        }
+        Some(rel.y as usize * self.width + rel.x as usize)
+    }
+
+    pub fn contains(&self, pos: Vec2<i32>) -> bool {
+        self.index(pos).is_some()
+    }
+
+    pub fn get(&self, pos: Vec2<i32>) -> Option<&T> {
+        self.index(pos).map(|i| &self.cells[i])
+    }
+
+    pub fn get_mut(&mut self, pos: Vec2<i32>) -> Option<&mut T> {
+        self.index(pos).map(|i| &mut self.cells[i])
+    }
+
+    /// Sets the cell, returns false when `pos` is outside the grid.
+    pub fn set(&mut self, pos: Vec2<i32>, value: T) -> bool {
+        match self.get_mut(pos) {
+            Some(cell) => {
+                *cell = value;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Vec2<i32>, &T)> {
+        self.cells.iter().enumerate().map(|(i, v)| {
+            let pos = Vec2::new((i % self.width) as i32, (i / self.width) as i32);
+            (pos + self.origin, v)
+        })
+    }
+}
@@ -0,0 +1,97 @@
+use super::{Game, Player, Tile, TileGrid, Vec2, WallKind};
+
+impl Game {
+    /// Height of the floor of `tile`, 0 unless set by [`Game::set_floor_height`].
+    pub fn floor_height(&self, tile: Vec2<i32>) -> f32 {
+        self.floor_heights.get(&tile).copied().unwrap_or_default()
+    }
+
+    /// Raises (or lowers, below 0) the floor of a tile, what stands on it included.
+    pub fn set_floor_height(&mut self, tile: Vec2<i32>, height: f32) {
+        if height == 0f32 {
+            self.floor_heights.remove(&tile);
+        } else {
+            self.floor_heights.insert(tile, height);
+        }
+    }
+
+    pub fn with_floor_height(mut self, tile: Vec2<i32>, height: f32) -> Self {
+        self.set_floor_height(tile, height);
+        self
+    }
+
+    /// Height of the player's eyes, above the floor they stand on.
+    pub fn eye_height(&self) -> f32 {
+        self.floor_height(self.player.position.floor::<i32>()) + Player::EYE_HEIGHT
+    }
+
+    /// Floor heights as a grid for lookups along rays, `None` when all floors are at 0.
+    pub fn floor_grid(&self) -> Option<TileGrid<f32>> {
+        let mut grid = TileGrid::covering(self.floor_heights.keys().copied(), 0f32)?;
+        for (tile, height) in &self.floor_heights {
+            grid.set(*tile, *height);
+        }
+        Some(grid)
+    }
+
+    /// Height of the top of what fills `tile`: its floor when empty, an open door or a
+    /// thin or diagonal wall, the top of the wall or closed door otherwise.
+    pub fn column_top(
+        &self,
+        tiles: &TileGrid<Tile>,
+        floors: Option<&TileGrid<f32>>,
+        tile: Vec2<i32>,
+    ) -> f32 {
+        let floor = floors
+            .and_then(|f| f.get(tile))
+            .copied()
+            .unwrap_or_default();
+        match tiles.get(tile) {
+            Some(Tile::Wall(w)) if self.walls[*w].kind == WallKind::Basic => {
+                floor + self.walls[*w].height
+            }
+            Some(Tile::Door(d)) if !self.doors[*d].is_open() => floor + 1f32,
+            _ => floor,
+        }
+    }
+
+    /// Whether the floors under a box of half size `radius` centered on `pos` are low
+    /// enough to be walked on from the player's tile.
+    pub fn can_step(&self, pos: Vec2, radius: f32) -> bool {
+        let current = self.floor_height(self.player.position.floor::<i32>());
+        let start = (pos - Vec2::scalar(radius)).floor::<i32>();
+        let end = (pos + Vec2::scalar(radius)).floor::<i32>();
+        (start.y..=end.y).all(|y| {
+            (start.x..=end.x)
+                .all(|x| self.floor_height(Vec2::new(x, y)) <= current + Player::MAX_STEP)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Game, Vec2, Wall, WallKind};
+
+    #[test]
+    fn steps_and_low_walls() {
+        let mut game = Game::new()
+            .with_player_position(Vec2::new(0.5, 0.5))
+            .with_player_direction(Vec2::new(1.0, 0.0))
+            .with_wall(Wall::new(WallKind::Basic, Vec2::new(0, -1)))
+            .with_wall(Wall::new(WallKind::Basic, Vec2::new(2, 0)).with_height(0.25))
+            .with_wall(Wall::new(WallKind::Basic, Vec2::new(4, 0)))
+            .with_floor_height(Vec2::new(0, 2), 0.25)
+            .with_floor_height(Vec2::new(0, 3), 0.5)
+            .with_minimap(None);
+        game.explore();
+        // seen over the low wall
+        assert!(game.is_explored(Vec2::new(3, 0)));
+        assert!(!game.is_explored(Vec2::new(5, 0)));
+
+        assert!(game.can_step(Vec2::new(0.5, 2.5), 0.3));
+        assert!(!game.can_step(Vec2::new(0.5, 3.5), 0.3));
+        game.player.position = Vec2::new(0.5, 2.5);
+        assert_eq!(game.eye_height(), 0.75);
+        assert!(game.can_step(Vec2::new(0.5, 3.5), 0.3));
+    }
+}
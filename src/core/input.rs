@@ -0,0 +1,68 @@
+/// Simulation rate, the game state only advances in whole tics.
+pub const TICS_PER_SECOND: u32 = 70;
+
+/// Player input for a single tic, the only thing driving the simulation besides its seed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TicCmd {
+    /// Forward (positive) or backward speed, full speed at `i8::MAX`.
+    pub forward: i8,
+    /// Rightward (positive) or leftward speed, full speed at `i8::MAX`.
+    pub strafe: i8,
+    /// Clockwise turn in [`TicCmd::TURN_UNIT`] steps.
+    pub turn: i16,
+    pub buttons: u8,
+}
+
+impl TicCmd {
+    pub const FIRE: u8 = 1 << 0;
+    pub const USE: u8 = 1 << 1;
+    pub const RUN: u8 = 1 << 2;
+
+    /// Degrees per `turn` step.
+    pub const TURN_UNIT: f32 = 0.25;
+
+    pub fn new(forward: i8, strafe: i8, turn: i16, buttons: u8) -> Self {
+        Self {
+            forward,
+            strafe,
+            turn,
+            buttons,
+        }
+    }
+
+    pub fn pressed(&self, button: u8) -> bool {
+        self.buttons & button != 0
+    }
+
+    /// Turn expressed in degrees.
+    pub fn turn_degrees(&self) -> f32 {
+        self.turn as f32 * Self::TURN_UNIT
+    }
+}
+
+/// Converts frame times into a number of tics to simulate.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TicClock {
+    elapsed_ms: f32,
+}
+
+impl TicClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accumulates `dt_ms` and returns how many tics are due, capped to avoid
+    /// spiralling after the page was in the background.
+    pub fn advance(&mut self, dt_ms: f32) -> u32 {
+        const MAX_TICS: u32 = TICS_PER_SECOND / 2;
+        const TIC_MS: f32 = 1000f32 / TICS_PER_SECOND as f32;
+        self.elapsed_ms += dt_ms.max(0f32);
+        let tics = (self.elapsed_ms / TIC_MS) as u32;
+        self.elapsed_ms -= tics as f32 * TIC_MS;
+        if tics > MAX_TICS {
+            self.elapsed_ms = 0f32;
+            return MAX_TICS;
+        }
+        tics
+    }
+}
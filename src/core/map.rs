@@ -0,0 +1,214 @@
+use super::{Difficulty, Door, DoorLock, Entity, EntityKind, Error, Player, Vec2, Wall, WallKind};
+
+/// Wall tiles of the original maps, each value is a wall texture.
+const WALL_TILES: std::ops::RangeInclusive<u16> = 1..=63;
+/// Doors, even codes are vertical doors, odd ones horizontal. Each pair has its own lock.
+const DOOR_TILES: std::ops::RangeInclusive<u16> = 90..=101;
+/// Player starts, facing north, east, south and west.
+const PLAYER_STARTS: std::ops::RangeInclusive<u16> = 19..=22;
+/// Static objects (decorations and pickups).
+const STATICS: std::ops::RangeInclusive<u16> = 23..=74;
+/// Static objects blocking the player.
+const BLOCKING_STATICS: [u16; 14] = [24, 25, 26, 28, 30, 31, 33, 34, 35, 36, 39, 40, 41, 45];
+
+/// Actors are laid out as blocks of 8 codes (4 standing + 4 patrolling directions),
+/// repeated with a stride for each difficulty tier they appear in.
+const ACTORS: [(EntityKind, u16, u16); 5] = [
+    (EntityKind::Guard, 108, 36),
+    (EntityKind::Officer, 116, 36),
+    (EntityKind::SS, 126, 36),
+    (EntityKind::Dog, 134, 36),
+    (EntityKind::Mutant, 216, 18),
+];
+/// Minimum difficulty for each actor tier.
+const ACTOR_TIERS: [Difficulty; 3] = [Difficulty::Baby, Difficulty::Medium, Difficulty::Hard];
+
+/// A level in the original two-planes layout: a tile plane (walls) and an object plane
+/// (player start, actors, statics).
+#[derive(Debug, Clone)]
+pub struct Map {
+    pub width: usize,
+    pub height: usize,
+    pub tiles: Vec<u16>,
+    pub objects: Vec<u16>,
+}
+
+impl Map {
+    pub fn new(
+        width: usize,
+        height: usize,
+        tiles: Vec<u16>,
+        objects: Vec<u16>,
+    ) -> crate::Result<Self> {
+        for (name, plane) in [("tile", &tiles), ("object", &objects)] {
+            if plane.len() != width * height {
+                return Err(Error::InvalidMap(format!(
+                    "{} plane has {} entries, expected {}x{}",
+                    name,
+                    plane.len(),
+                    width,
+                    height
+                )));
+            }
+        }
+        Ok(Self {
+            width,
+            height,
+            tiles,
+            objects,
+        })
+    }
+
+    pub fn tile(&self, x: usize, y: usize) -> Option<u16> {
+        self.plane_at(&self.tiles, x, y)
+    }
+
+    pub fn object(&self, x: usize, y: usize) -> Option<u16> {
+        self.plane_at(&self.objects, x, y)
+    }
+
+    fn plane_at(&self, plane: &[u16], x: usize, y: usize) -> Option<u16> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        plane.get(y * self.width + x).copied()
+    }
+
+    fn cells(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (0..self.height).flat_map(move |y| (0..self.width).map(move |x| (x, y)))
+    }
+
+    pub fn walls(&self) -> Vec<Wall> {
+        self.cells()
+            .filter(|(x, y)| WALL_TILES.contains(&self.tile(*x, *y).unwrap_or_default()))
+            .map(|(x, y)| Wall::new(WallKind::Basic, Vec2::new(x as i32, y as i32)))
+            .collect()
+    }
+
+    pub fn doors(&self) -> Vec<Door> {
+        self.cells()
+            .filter_map(|(x, y)| {
+                let code = self.tile(x, y)?;
+                if !DOOR_TILES.contains(&code) {
+                    return None;
+                }
+                let code = code - DOOR_TILES.start();
+                let lock = match code / 2 {
+                    1 => DoorLock::Gold,
+                    2 => DoorLock::Silver,
+                    5 => DoorLock::Elevator,
+                    _ => DoorLock::None,
+                };
+                Some(Door::new(
+                    Vec2::new(x as i32, y as i32),
+                    code.is_multiple_of(2),
+                    lock,
+                ))
+            })
+            .collect()
+    }
+
+    pub fn player_start(&self) -> Option<Player> {
+        self.cells().find_map(|(x, y)| {
+            let code = self.object(x, y)?;
+            if !PLAYER_STARTS.contains(&code) {
+                return None;
+            }
+            let dir = match code - PLAYER_STARTS.start() {
+                0 => Vec2::new(0f32, -1f32),
+                1 => Vec2::new(1f32, 0f32),
+                2 => Vec2::new(0f32, 1f32),
+                _ => Vec2::new(-1f32, 0f32),
+            };
+            Some(Player::new(tile_center(x, y), dir))
+        })
+    }
+
+    /// Entities spawned at the given difficulty, actors of higher tiers are left out.
+    pub fn entities(&self, difficulty: Difficulty) -> Vec<Entity> {
+        self.cells()
+            .filter_map(|(x, y)| decode_object(self.object(x, y)?, tile_center(x, y), difficulty))
+            .collect()
+    }
+}
+
+fn tile_center(x: usize, y: usize) -> Vec2 {
+    Vec2::new(x as f32 + 0.5, y as f32 + 0.5)
+}
+
+fn decode_object(code: u16, position: Vec2, difficulty: Difficulty) -> Option<Entity> {
+    if STATICS.contains(&code) {
+        let kind = match code {
+            43 => EntityKind::GoldKey,
+            44 => EntityKind::SilverKey,
+            47 => EntityKind::Food,
+            48 => EntityKind::FirstAid,
+            49 => EntityKind::Clip,
+            52 => EntityKind::Cross,
+            53 => EntityKind::Chalice,
+            54 => EntityKind::Chest,
+            55 => EntityKind::Crown,
+            56 => EntityKind::ExtraLife,
+            _ => EntityKind::Decoration {
+                sprite: code - STATICS.start(),
+                blocking: BLOCKING_STATICS.contains(&code),
+            },
+        };
+        return Some(Entity::spawn(kind, position, difficulty));
+    }
+    for (kind, base, stride) in ACTORS {
+        for (tier, min_difficulty) in ACTOR_TIERS.iter().enumerate() {
+            let start = base + tier as u16 * stride;
+            if !(start..start + 8).contains(&code) {
+                continue;
+            }
+            if difficulty < *min_difficulty {
+                return None;
+            }
+            let variant = code - start;
+            let dir = match variant % 4 {
+                0 => Vec2::new(1f32, 0f32),
+                1 => Vec2::new(0f32, -1f32),
+                2 => Vec2::new(-1f32, 0f32),
+                _ => Vec2::new(0f32, 1f32),
+            };
+            return Some(
+                Entity::spawn(kind, position, difficulty)
+                    .with_direction(dir)
+                    .with_patrolling(variant >= 4),
+            );
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Difficulty, EntityKind, Map};
+
+    fn map_with_objects(objects: &[u16]) -> Map {
+        Map::new(objects.len(), 1, vec![0; objects.len()], objects.to_vec()).unwrap()
+    }
+
+    #[test]
+    fn actors_per_difficulty() {
+        // easy-tier guard, medium-tier officer, hard-tier SS
+        let map = map_with_objects(&[108, 152, 198]);
+        let kinds = |d| map.entities(d).iter().map(|e| e.kind).collect::<Vec<_>>();
+        assert_eq!(kinds(Difficulty::Baby), vec![EntityKind::Guard]);
+        assert_eq!(kinds(Difficulty::Easy), vec![EntityKind::Guard]);
+        assert_eq!(
+            kinds(Difficulty::Medium),
+            vec![EntityKind::Guard, EntityKind::Officer]
+        );
+        assert_eq!(
+            kinds(Difficulty::Hard),
+            vec![EntityKind::Guard, EntityKind::Officer, EntityKind::SS]
+        );
+    }
+
+    #[test]
+    fn invalid_plane_size() {
+        assert!(Map::new(2, 2, vec![0; 4], vec![0; 3]).is_err());
+    }
+}
@@ -9,7 +9,6 @@ use std::ops::Mul;
 use std::ops::MulAssign;
 use std::ops::Sub;
 use std::ops::SubAssign;
-use std::slice::IterMut;
 
 use super::Error;
 
@@ -39,8 +38,8 @@ impl<T: Copy> Vec2<T> {
         Self { x, y }
     }
     
-    pub const fn scalar(v: f32) -> Vec2 {
-        Vec2::new(v, v)
+    pub const fn scalar(v: T) -> Self {
+        Self::new(v, v)
     }
 }
 
@@ -54,6 +53,10 @@ impl<T: Copy + Mul<T, Output = T> + Add<T, Output = T>> Vec2<T> {
     pub fn sq_magn(&self) -> T {
         self.x * self.x + self.y * self.y
     }
+
+    pub fn dot(&self, other: Self) -> T {
+        self.x * other.x + self.y * other.y
+    }
 }
 
 pub trait Sqrt {
@@ -136,11 +139,21 @@ impl<T: Copy + ToPrimitive> Vec2<T> {
     }
 }
 
+impl<T: Copy + ToPrimitive> Vec2<T> {
+    /// Rounds both components toward negative infinity, i.e. the tile containing the point.
+    pub fn floor<To: Copy + ToPrimitive + FromPrimitive<f64>>(&self) -> Vec2<To> {
+        Vec2::new(
+            To::from_primitive(self.x.to_primitive().floor()),
+            To::from_primitive(self.y.to_primitive().floor()),
+        )
+    }
+}
+
 impl<T: Copy + MulAssign<T>> Vec2<T> {
     pub fn scaled(&self, by: T) -> Self {
         let mut ret = *self;
         ret.scale(by);
-        return ret;
+        ret
     }
 
     pub fn scale(&mut self, by: T) -> &mut Self {
@@ -220,8 +233,9 @@ impl<
         let cos_theta = T::from_primitive(angle_radians.cos());
         let sin_theta = T::from_primitive(angle_radians.sin());
 
-        self.x = self.x * cos_theta - self.y * sin_theta;
+        let x = self.x * cos_theta - self.y * sin_theta;
         self.y = self.x * sin_theta + self.y * cos_theta;
+        self.x = x;
         self
     }
 
@@ -308,7 +322,7 @@ impl<
     
     pub fn samples(&mut self) -> Vec<Vec2<T>> {
         let mut ret = vec![];
-        while let Some(sample) = self.next() {
+        for sample in self.by_ref() {
             ret.push(sample);
         }
         ret
@@ -345,6 +359,7 @@ impl<
     }
 }
 
+#[derive(Clone, Copy, PartialEq)]
 pub struct Rect<T: Copy> {
     start: Vec2<T>,
     end: Vec2<T>
@@ -364,6 +379,36 @@ impl<T: Copy> Rect<T> {
     }
 }
 
+impl<T: Copy + PartialOrd> Rect<T> {
+    pub fn start(&self) -> Vec2<T> {
+        self.start
+    }
+
+    pub fn end(&self) -> Vec2<T> {
+        self.end
+    }
+
+    /// Whether `p` lies inside, `end` being exclusive.
+    pub fn contains(&self, p: Vec2<T>) -> bool {
+        p.x >= self.start.x && p.y >= self.start.y && p.x < self.end.x && p.y < self.end.y
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.end.x <= self.start.x || self.end.y <= self.start.y
+    }
+
+    /// Overlapping area of both rects, `None` when they don't overlap.
+    pub fn intersection(&self, other: &Self) -> Option<Self> {
+        let max = |a: T, b: T| if a > b { a } else { b };
+        let min = |a: T, b: T| if a < b { a } else { b };
+        let ret = Self::new(
+            Vec2::new(max(self.start.x, other.start.x), max(self.start.y, other.start.y)),
+            Vec2::new(min(self.end.x, other.end.x), min(self.end.y, other.end.y)),
+        );
+        (!ret.is_empty()).then_some(ret)
+    }
+}
+
 impl<T: Copy + SubAssign<T>> Rect<T> {
     pub fn area(&self) -> Vec2<T> {
         self.end - self.start
@@ -381,4 +426,10 @@ mod tests {
         println!("samples: {:#?}", samples);
         assert_eq!(samples.len(), 3)
     }
+
+    #[test]
+    fn rotate() {
+        let v = Vec2::new(0f32, -1f32).rotated(90.0);
+        assert!((v.x - 1.0).abs() < 1e-6 && v.y.abs() < 1e-6);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,242 @@
+use super::{Color, DoorLock, Game, PixelBuffer, Player, Rect, TileGrid, Vec2};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MinimapMode {
+    /// The map is fixed, north pointing up.
+    #[default]
+    NorthUp,
+    /// The map turns around the player, who always faces up.
+    Rotating,
+}
+
+/// Layout and colors of the minimap, `None` colors are not drawn.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MinimapStyle {
+    /// Top left corner on screen, in pixels.
+    pub position: Vec2<usize>,
+    /// Size on screen, in pixels.
+    pub size: Vec2<usize>,
+    /// Pixels per world tile.
+    pub tile_size: f32,
+    pub mode: MinimapMode,
+    pub background: Option<Color>,
+    pub border: Option<Color>,
+    pub wall: Color,
+    pub door: Color,
+    pub locked_door: Color,
+    pub player: Color,
+    pub enemy: Option<Color>,
+    pub pickup: Option<Color>,
+    pub frustum: Option<Color>,
+    /// How far the view cone reaches, in tiles.
+    pub frustum_length: f32,
+}
+
+impl Default for MinimapStyle {
+    fn default() -> Self {
+        Self {
+            position: Vec2::new(0, 0),
+            size: Vec2::new(100, 100),
+            tile_size: 5f32,
+            mode: MinimapMode::NorthUp,
+            background: Some(Color::black()),
+            border: Some(Color::blue()),
+            wall: Color::new(160, 160, 160, 255),
+            door: Color::new(0, 160, 160, 255),
+            locked_door: Color::new(200, 160, 0, 255),
+            player: Color::red(),
+            enemy: Some(Color::new(255, 120, 0, 255)),
+            pickup: Some(Color::new(255, 255, 0, 255)),
+            frustum: Some(Color::new(0, 70, 0, 255)),
+            frustum_length: 6f32,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum MinimapTile {
+    Empty,
+    /// Index in `Game::walls`.
+    Wall(usize),
+    Door(DoorLock),
+}
+
+impl MinimapStyle {
+    fn half_size(&self) -> Vec2 {
+        self.size.cast::<f32>() * Vec2::scalar(0.5)
+    }
+
+    /// Converts a point relative to the minimap center (pixels) to world space.
+    fn screen_to_world(&self, player: &Player, screen: Vec2) -> Vec2 {
+        let rel = screen.scaled(1f32 / self.tile_size);
+        match self.mode {
+            MinimapMode::NorthUp => player.position + rel,
+            MinimapMode::Rotating => {
+                let dir = player.direction;
+                let right = Vec2::new(-dir.y, dir.x);
+                player.position + right.scaled(rel.x) + dir.scaled(-rel.y)
+            }
+        }
+    }
+
+    /// Converts a world position to a point relative to the minimap center (pixels).
+    fn world_to_screen(&self, player: &Player, world: Vec2) -> Vec2 {
+        let rel = world - player.position;
+        let rel = match self.mode {
+            MinimapMode::NorthUp => rel,
+            MinimapMode::Rotating => {
+                let dir = player.direction;
+                let right = Vec2::new(-dir.y, dir.x);
+                Vec2::new(rel.dot(right), -rel.dot(dir))
+            }
+        };
+        rel.scaled(self.tile_size)
+    }
+
+    fn screen_rect(&self) -> Rect<isize> {
+        let start = self.position.cast::<isize>();
+        Rect::new(start, start + self.size.cast::<isize>())
+    }
+}
+
+impl Game {
+    /// Area of the world shown by the minimap, whatever its rotation.
+    pub fn minimap_world_bounds(&self, style: &MinimapStyle) -> Rect<f32> {
+        let half = style.half_size().scaled(1f32 / style.tile_size);
+        let reach = match style.mode {
+            MinimapMode::NorthUp => half,
+            MinimapMode::Rotating => Vec2::scalar(half.magn()),
+        };
+        Rect::new(self.player.position - reach, self.player.position + reach)
+    }
+
+    fn minimap_tiles(&self, bounds: &Rect<f32>) -> TileGrid<MinimapTile> {
+        let start = bounds.start().floor::<i32>();
+        let end = bounds.end().floor::<i32>();
+        let mut grid = TileGrid::new(
+            start,
+            (end.x - start.x + 1) as usize,
+            (end.y - start.y + 1) as usize,
+            MinimapTile::Empty,
+        );
+        for (i, wall) in self.walls.iter().enumerate() {
+            grid.set(wall.position, MinimapTile::Wall(i));
+        }
+        for door in &self.doors {
+            grid.set(door.position, MinimapTile::Door(door.lock));
+        }
+        grid
+    }
+
+    pub fn render_minimap(&self, buf: &mut PixelBuffer, style: &MinimapStyle) {
+        if style.tile_size <= 0f32 {
+            return;
+        }
+        let bounds = self.minimap_world_bounds(style);
+        let tiles = self.minimap_tiles(&bounds);
+        let half_size = style.half_size();
+        let cos_half_fov = (self.camera.horizontal_fov().to_radians() * 0.5).cos();
+        let half_pixel = 0.5 / style.tile_size;
+        for y in 0..style.size.y {
+            for x in 0..style.size.x {
+                let screen = Vec2::new(x as f32 + 0.5, y as f32 + 0.5) - half_size;
+                let world = style.screen_to_world(&self.player, screen);
+                let color = match tiles.get(world.floor::<i32>()) {
+                    Some(MinimapTile::Wall(w)) if self.walls[*w].overlaps(world, half_pixel) => {
+                        Some(style.wall)
+                    }
+                    Some(MinimapTile::Door(DoorLock::None)) => Some(style.door),
+                    Some(MinimapTile::Door(_)) => Some(style.locked_door),
+                    _ => {
+                        let rel = world - self.player.position;
+                        let dist = rel.magn();
+                        let in_frustum = dist <= style.frustum_length
+                            && rel.dot(self.player.direction) >= dist * cos_half_fov;
+                        style.frustum.filter(|_| in_frustum).or(style.background)
+                    }
+                };
+                if let Some(color) = color {
+                    let _ = buf.put(style.position.x + x, style.position.y + y, color);
+                }
+            }
+        }
+
+        let center = style.position.cast::<f32>() + half_size;
+        let previous_clip = buf.set_clip(Some(style.screen_rect()));
+        let dot_at = |buf: &mut PixelBuffer, pos: Vec2, color: Color| {
+            let pos = pos.floor::<isize>();
+            buf.fill_rect(
+                Rect::new(pos - Vec2::new(1, 1), pos + Vec2::new(2, 2)),
+                color,
+            );
+        };
+        for entity in self.entities.iter().filter(|e| e.is_alive()) {
+            if !bounds.contains(entity.position) {
+                continue;
+            }
+            let color = if entity.kind.is_enemy() {
+                style.enemy
+            } else if entity.kind.is_pickup() {
+                style.pickup
+            } else {
+                None
+            };
+            if let Some(color) = color {
+                dot_at(
+                    buf,
+                    center + style.world_to_screen(&self.player, entity.position),
+                    color,
+                );
+            }
+        }
+        dot_at(buf, center, style.player);
+        let heading = self.player.position + self.player.direction.scaled(2f32);
+        let heading = center + style.world_to_screen(&self.player, heading);
+        buf.line(
+            center.floor::<isize>(),
+            heading.floor::<isize>(),
+            style.player,
+        );
+
+        if let Some(border) = style.border {
+            buf.rect(style.screen_rect(), border);
+        }
+        buf.set_clip(previous_clip);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Color, Game, MinimapMode, MinimapStyle, PixelBuffer, Vec2, Wall, WallKind};
+
+    #[test]
+    fn walls_are_projected() {
+        let style = MinimapStyle {
+            size: Vec2::new(50, 50),
+            tile_size: 10.0,
+            border: None,
+            frustum: None,
+            ..Default::default()
+        };
+        let mut buf = PixelBuffer::new(50, 50);
+        // the player faces east, a wall 2 tiles east of it
+        let game = Game::new()
+            .with_player_position(Vec2::new(0.5, 0.5))
+            .with_player_direction(Vec2::new(1.0, 0.0))
+            .with_wall(Wall::new(WallKind::Basic, Vec2::new(2, 0)));
+        game.render_minimap(&mut buf, &style);
+        assert_eq!(buf.get(45, 22), Some(&style.wall));
+        assert_eq!(buf.get(25, 5), Some(&Color::black()));
+
+        game.render_minimap(
+            &mut buf,
+            &MinimapStyle {
+                mode: MinimapMode::Rotating,
+                ..style
+            },
+        );
+        // rotated: what is ahead is drawn above the player
+        assert_eq!(buf.get(22, 5), Some(&style.wall));
+        assert_eq!(buf.get(45, 25), Some(&Color::black()));
+    }
+}
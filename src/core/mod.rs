@@ -1,9 +1,60 @@
 pub mod pixel_buf;
+pub mod blit;
 pub mod game;
 pub mod math;
 pub mod error;
+pub mod difficulty;
+pub mod entity;
+pub mod map;
+pub mod door;
+pub mod save;
+pub mod input;
+pub mod demo;
+pub mod rng;
+pub mod audio;
+pub mod grid;
+pub mod minimap;
+pub mod raycast;
+pub mod automap;
+pub mod camera;
+pub mod scale;
+pub mod palette;
+pub mod particle;
+pub mod transition;
+pub mod fog;
+pub mod render;
+pub mod teleport;
+pub mod texture;
+pub mod height;
+pub mod animation;
+pub mod decal;
 
 pub use pixel_buf::*;
+pub use blit::*;
 pub use game::*;
 pub use math::*;
 pub use error::*;
+pub use difficulty::*;
+pub use entity::*;
+pub use map::*;
+pub use door::*;
+pub use save::*;
+pub use input::*;
+pub use demo::*;
+pub use rng::*;
+pub use audio::*;
+pub use grid::*;
+pub use minimap::*;
+pub use raycast::*;
+pub use automap::*;
+pub use camera::*;
+pub use scale::*;
+pub use palette::*;
+pub use particle::*;
+pub use transition::*;
+pub use fog::*;
+pub use render::*;
+pub use teleport::*;
+pub use texture::*;
+pub use animation::*;
+pub use decal::*;
@@ -0,0 +1,373 @@
+use super::{Color, Error, PixelBuffer};
+
+/// 256 colors indexed by the pixels of an [`IndexedBuffer`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Palette {
+    pub colors: [Color; 256],
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::grayscale()
+    }
+}
+
+impl Palette {
+    pub fn grayscale() -> Self {
+        let mut colors = [Color::black(); 256];
+        for (i, c) in colors.iter_mut().enumerate() {
+            *c = Color::new(i as u8, i as u8, i as u8, 255);
+        }
+        Self { colors }
+    }
+
+    /// 768 bytes of 8 bit RGB triplets.
+    pub fn from_rgb(data: &[u8]) -> crate::Result<Self> {
+        Self::parse(data, |v| v)
+    }
+
+    /// 768 bytes of 6 bit RGB triplets, the VGA DAC format of the original `GAMEPAL`.
+    pub fn from_vga(data: &[u8]) -> crate::Result<Self> {
+        Self::parse(data, |v| {
+            let v = v & 0x3f;
+            (v << 2) | (v >> 4)
+        })
+    }
+
+    fn parse(data: &[u8], channel: impl Fn(u8) -> u8) -> crate::Result<Self> {
+        if data.len() != 256 * 3 {
+            return Err(Error::InvalidImage(format!(
+                "palette is {} bytes instead of 768",
+                data.len()
+            )));
+        }
+        let mut colors = [Color::black(); 256];
+        for (c, rgb) in colors.iter_mut().zip(data.chunks_exact(3)) {
+            *c = Color::new(channel(rgb[0]), channel(rgb[1]), channel(rgb[2]), 255);
+        }
+        Ok(Self { colors })
+    }
+
+    pub fn get(&self, index: u8) -> Color {
+        self.colors[index as usize]
+    }
+
+    /// Converts `width` x `height` palette indices to an RGBA texture.
+    pub fn texture(
+        &self,
+        width: usize,
+        height: usize,
+        indices: &[u8],
+    ) -> crate::Result<PixelBuffer> {
+        if indices.len() != width * height {
+            return Err(Error::InvalidImage(format!(
+                "{} indexed pixels for a {}x{} image",
+                indices.len(),
+                width,
+                height
+            )));
+        }
+        let mut ret = PixelBuffer::new(width, height);
+        for (dst, src) in ret.data.iter_mut().zip(indices) {
+            *dst = self.get(*src);
+        }
+        Ok(ret)
+    }
+}
+
+/// Closest [`Palette`] entry of every 15 bit color, to convert RGBA frames to an
+/// [`IndexedBuffer`] with a table lookup per pixel.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ColorMap {
+    palette: Palette,
+    indices: Vec<u8>,
+}
+
+impl ColorMap {
+    pub fn new(palette: Palette) -> Self {
+        let expand = |v: usize| ((v << 3) | (v >> 2)) as i32;
+        let indices = (0..1 << 15)
+            .map(|rgb| {
+                let (r, g, b) = (expand(rgb >> 10), expand((rgb >> 5) & 31), expand(rgb & 31));
+                let distance = |c: &Color| {
+                    let (dr, dg, db) = (c.r as i32 - r, c.g as i32 - g, c.b as i32 - b);
+                    dr * dr + dg * dg + db * db
+                };
+                (0..=255u8)
+                    .min_by_key(|i| distance(&palette.colors[*i as usize]))
+                    .unwrap_or_default()
+            })
+            .collect();
+        Self { palette, indices }
+    }
+
+    pub fn palette(&self) -> &Palette {
+        &self.palette
+    }
+
+    pub fn index(&self, color: Color) -> u8 {
+        let rgb = ((color.r as usize >> 3) << 10)
+            | ((color.g as usize >> 3) << 5)
+            | (color.b as usize >> 3);
+        self.indices[rgb]
+    }
+}
+
+/// 8 bit framebuffer, converted to RGBA through a [`Palette`] when presented.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IndexedBuffer {
+    pub data: Vec<u8>,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl IndexedBuffer {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            data: vec![0; width * height],
+            width,
+            height,
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<u8> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        Some(self.data[y * self.width + x])
+    }
+
+    /// Sets a pixel, ignored outside the buffer.
+    pub fn plot(&mut self, x: isize, y: isize, index: u8) {
+        if x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height {
+            self.data[y as usize * self.width + x as usize] = index;
+        }
+    }
+
+    pub fn fill(&mut self, index: u8) {
+        self.data.fill(index);
+    }
+
+    /// Replaces the pixels with the closest colors of `map` to the ones of `frame`,
+    /// taking its size.
+    pub fn quantize(&mut self, frame: &PixelBuffer, map: &ColorMap) {
+        self.width = frame.width;
+        self.height = frame.height;
+        self.data.clear();
+        self.data.extend(frame.data.iter().map(|c| map.index(*c)));
+    }
+
+    /// Converts the pixels through `palette` into the top left corner of `out`.
+    pub fn present(&self, palette: &Palette, out: &mut PixelBuffer) {
+        let width = self.width.min(out.width);
+        for y in 0..self.height.min(out.height) {
+            let src = &self.data[y * self.width..y * self.width + width];
+            let dst = &mut out.data[y * out.width..y * out.width + width];
+            for (d, s) in dst.iter_mut().zip(src) {
+                *d = palette.colors[*s as usize];
+            }
+        }
+    }
+}
+
+/// Full screen color shifts of the original: a red flash when hurt, a gold one on
+/// pickups and fades on death or level changes. Advanced once per tic.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PaletteEffects {
+    /// Remaining damage points, decreasing by one per tic.
+    damage: u32,
+    /// Remaining tics of the bonus flash.
+    bonus: u32,
+    fade: Option<Fade>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Fade {
+    color: Color,
+    from: f32,
+    to: f32,
+    tics: u32,
+    elapsed: u32,
+}
+
+impl Fade {
+    fn amount(&self) -> f32 {
+        let t = (self.elapsed as f32 / self.tics.max(1) as f32).min(1f32);
+        self.from + (self.to - self.from) * t
+    }
+}
+
+impl PaletteEffects {
+    const RED_SHIFTS: u32 = 6;
+    const RED_STEPS: f32 = 8.0;
+    const WHITE_SHIFTS: u32 = 3;
+    const WHITE_STEPS: f32 = 20.0;
+    const WHITE_TICS: u32 = 6;
+    const RED: Color = Color::new(255, 0, 0, 255);
+    const GOLD: Color = Color::new(255, 251, 0, 255);
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Flashes red, longer and stronger with the damage taken.
+    pub fn damage(&mut self, points: u32) {
+        self.damage += points;
+    }
+
+    /// Short gold flash, for pickups.
+    pub fn bonus(&mut self) {
+        self.bonus = Self::WHITE_SHIFTS * Self::WHITE_TICS;
+    }
+
+    /// Fades the screen to `color` over `tics`, it stays there until [`Self::fade_in`].
+    pub fn fade_out(&mut self, color: Color, tics: u32) {
+        let from = self.fade.map(|f| f.amount()).unwrap_or_default();
+        self.fade = Some(Fade {
+            color,
+            from,
+            to: 1f32,
+            tics,
+            elapsed: 0,
+        });
+    }
+
+    /// Fades back from the color of the last [`Self::fade_out`] over `tics`.
+    pub fn fade_in(&mut self, tics: u32) {
+        if let Some(fade) = self.fade {
+            self.fade = Some(Fade {
+                from: fade.amount(),
+                to: 0f32,
+                tics,
+                elapsed: 0,
+                ..fade
+            });
+        }
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.damage > 0 || self.bonus > 0 || self.fade.is_some()
+    }
+
+    pub fn tick(&mut self) {
+        self.damage = self.damage.saturating_sub(1);
+        self.bonus = self.bonus.saturating_sub(1);
+        if let Some(fade) = self.fade.as_mut() {
+            fade.elapsed = (fade.elapsed + 1).min(fade.tics);
+            if fade.to == 0f32 && fade.elapsed == fade.tics {
+                self.fade = None;
+            }
+        }
+    }
+
+    /// Color shift in effect, if any, as the color blended to and its weight.
+    fn shift(&self) -> Option<(Color, f32)> {
+        if self.damage > 0 {
+            let step = (self.damage / 10 + 1).min(Self::RED_SHIFTS);
+            Some((Self::RED, step as f32 / Self::RED_STEPS))
+        } else if self.bonus > 0 {
+            let step = self.bonus.div_ceil(Self::WHITE_TICS);
+            Some((Self::GOLD, step as f32 / Self::WHITE_STEPS))
+        } else {
+            None
+        }
+    }
+
+    pub fn apply_color(&self, color: Color) -> Color {
+        let mut color = color;
+        if let Some((target, amount)) = self.shift() {
+            color = color.lerp(target, amount);
+        }
+        if let Some(fade) = self.fade {
+            color = color.lerp(fade.color, fade.amount());
+        }
+        color
+    }
+
+    /// Palette with the effects applied, for the indexed path: only 256 colors are
+    /// shifted instead of every pixel.
+    pub fn apply(&self, palette: &Palette) -> Palette {
+        let mut ret = palette.clone();
+        if self.is_active() {
+            ret.colors
+                .iter_mut()
+                .for_each(|c| *c = self.apply_color(*c));
+        }
+        ret
+    }
+
+    /// Applies the effects to every pixel of an RGBA frame.
+    pub fn apply_buffer(&self, buf: &mut PixelBuffer) {
+        if self.is_active() {
+            buf.data.iter_mut().for_each(|c| *c = self.apply_color(*c));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ColorMap, IndexedBuffer, Palette, PaletteEffects};
+    use crate::{Color, PixelBuffer};
+
+    #[test]
+    fn textures_through_palette() {
+        let mut data = vec![0u8; 768];
+        data[3..6].copy_from_slice(&[63, 32, 0]);
+        let palette = Palette::from_vga(&data).unwrap();
+        assert_eq!(palette.get(1), Color::new(255, 130, 0, 255));
+        assert!(Palette::from_vga(&data[1..]).is_err());
+
+        let texture = palette.texture(2, 1, &[0, 1]).unwrap();
+        assert_eq!(
+            texture.data,
+            vec![Color::black(), Color::new(255, 130, 0, 255)]
+        );
+        assert!(palette.texture(2, 2, &[0, 1]).is_err());
+    }
+
+    #[test]
+    fn present_through_palette() {
+        let mut palette = Palette::grayscale();
+        palette.colors[1] = Color::new(255, 128, 0, 255);
+        let map = ColorMap::new(palette.clone());
+        let mut frame = IndexedBuffer::new(0, 0);
+        let mut rgba = PixelBuffer::new(3, 1);
+        rgba.data = vec![Color::white(), Color::new(250, 130, 4, 255), Color::black()];
+        frame.quantize(&rgba, &map);
+        assert_eq!(frame.data, vec![255, 1, 0]);
+
+        let mut effects = PaletteEffects::new();
+        effects.damage(5);
+        let mut out = PixelBuffer::new(3, 1);
+        frame.present(&effects.apply(&palette), &mut out);
+        assert_eq!(out.data[2], effects.apply_color(Color::black()));
+        assert_eq!(out.data[1], effects.apply_color(palette.get(1)));
+    }
+
+    #[test]
+    fn effects_wear_off() {
+        let mut effects = PaletteEffects::new();
+        effects.damage(5);
+        let hurt = effects.apply_color(Color::black());
+        assert_eq!(hurt, Color::new(32, 0, 0, 255));
+        (0..5).for_each(|_| effects.tick());
+        assert_eq!(effects.apply_color(Color::black()), Color::black());
+
+        effects.fade_out(Color::white(), 2);
+        effects.tick();
+        assert_eq!(
+            effects.apply_color(Color::black()),
+            Color::new(128, 128, 128, 255)
+        );
+        effects.tick();
+        effects.tick();
+        assert_eq!(effects.apply_color(Color::black()), Color::white());
+        effects.fade_in(1);
+        effects.tick();
+        assert!(!effects.is_active());
+    }
+}
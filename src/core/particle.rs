@@ -0,0 +1,259 @@
+use std::f32::consts::TAU;
+
+use super::{Color, Rng, Vec2, TICS_PER_SECOND};
+
+/// Look and motion of the particles of an [`Emitter`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ParticleStyle {
+    pub color: Color,
+    /// Fades out over its lifetime.
+    pub fade: bool,
+    /// Horizontal speed in a random direction, in tiles per second.
+    pub speed: f32,
+    /// Upward speed, in tiles per second.
+    pub rise: f32,
+    /// Downward acceleration in tiles per second squared, negative to float up.
+    pub gravity: f32,
+    /// Tics before it disappears.
+    pub lifetime: u16,
+    /// Width and height, in tiles.
+    pub size: f32,
+}
+
+impl ParticleStyle {
+    pub const BLOOD: Self = Self {
+        color: Color::new(168, 0, 0, 255),
+        fade: false,
+        speed: 1.0,
+        rise: 1.0,
+        gravity: 6.0,
+        lifetime: 35,
+        size: 0.04,
+    };
+    pub const SPARKS: Self = Self {
+        color: Color::new(252, 252, 84, 255),
+        fade: true,
+        speed: 2.0,
+        rise: 1.5,
+        gravity: 8.0,
+        lifetime: 14,
+        size: 0.02,
+    };
+    pub const SMOKE: Self = Self {
+        color: Color::new(120, 120, 120, 160),
+        fade: true,
+        speed: 0.2,
+        rise: 0.3,
+        gravity: -0.2,
+        lifetime: 105,
+        size: 0.08,
+    };
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Particle {
+    pub position: Vec2,
+    /// Height above the floor level 0, in tiles.
+    pub height: f32,
+    /// Horizontal velocity, in tiles per second.
+    pub velocity: Vec2,
+    /// Upward speed, in tiles per second.
+    pub rise: f32,
+    /// Tics since it was emitted.
+    pub age: u16,
+    pub style: ParticleStyle,
+}
+
+impl Particle {
+    /// Color at its current age.
+    pub fn color(&self) -> Color {
+        let color = self.style.color;
+        if !self.style.fade {
+            return color;
+        }
+        let left = 1f32 - self.age as f32 / self.style.lifetime.max(1) as f32;
+        Color {
+            a: (color.a as f32 * left.max(0f32)) as u8,
+            ..color
+        }
+    }
+}
+
+/// Spawns bursts of particles at a world position.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Emitter {
+    pub position: Vec2,
+    pub height: f32,
+    pub style: ParticleStyle,
+    /// Particles per burst.
+    pub burst: u16,
+    /// Tics between bursts.
+    pub interval: u16,
+    /// Bursts left, `None` to emit until removed.
+    pub bursts: Option<u16>,
+    /// Tics before the next burst.
+    timer: u16,
+}
+
+impl Emitter {
+    /// Emitter of a single burst of 8 particles.
+    pub fn new(position: Vec2, height: f32, style: ParticleStyle) -> Self {
+        Self {
+            position,
+            height,
+            style,
+            burst: 8,
+            interval: 1,
+            bursts: Some(1),
+            timer: 0,
+        }
+    }
+
+    pub fn with_burst(mut self, burst: u16) -> Self {
+        self.burst = burst;
+        self
+    }
+
+    pub fn with_interval(mut self, interval: u16) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    pub fn with_bursts(mut self, bursts: Option<u16>) -> Self {
+        self.bursts = bursts;
+        self
+    }
+}
+
+/// Blood splats, sparks and smoke: particles and their emitters, advanced once per tic.
+/// They don't affect the game, so they have their own random numbers.
+#[derive(Clone, Debug)]
+pub struct ParticleSystem {
+    particles: Vec<Particle>,
+    emitters: Vec<Emitter>,
+    rng: Rng,
+}
+
+impl Default for ParticleSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ParticleSystem {
+    pub fn new() -> Self {
+        Self {
+            particles: vec![],
+            emitters: vec![],
+            rng: Rng::new(0),
+        }
+    }
+
+    pub fn particles(&self) -> &[Particle] {
+        &self.particles
+    }
+
+    pub fn emitters(&self) -> &[Emitter] {
+        &self.emitters
+    }
+
+    pub fn add_emitter(&mut self, emitter: Emitter) {
+        self.emitters.push(emitter);
+    }
+
+    /// Removes every particle and emitter.
+    pub fn clear(&mut self) {
+        self.particles.clear();
+        self.emitters.clear();
+    }
+
+    /// Emits the bursts due and moves the particles, which land on the floor of their
+    /// tile given by `floor`.
+    pub fn tick(&mut self, floor: impl Fn(Vec2<i32>) -> f32) {
+        const DT: f32 = 1f32 / TICS_PER_SECOND as f32;
+        for i in 0..self.emitters.len() {
+            let emitter = self.emitters[i];
+            if emitter.timer == 0 {
+                for _ in 0..emitter.burst {
+                    let particle = self.spawn(&emitter);
+                    self.particles.push(particle);
+                }
+            }
+            let emitter = &mut self.emitters[i];
+            if emitter.timer == 0 {
+                emitter.bursts = emitter.bursts.map(|b| b.saturating_sub(1));
+                emitter.timer = emitter.interval;
+            }
+            emitter.timer = emitter.timer.saturating_sub(1);
+        }
+        self.emitters.retain(|e| e.bursts != Some(0));
+
+        self.particles.retain_mut(|p| {
+            p.age += 1;
+            p.rise -= p.style.gravity * DT;
+            p.position += p.velocity.scaled(DT);
+            p.height += p.rise * DT;
+            let floor = floor(p.position.floor::<i32>());
+            if p.height <= floor {
+                p.height = floor;
+                p.velocity = Vec2::zero();
+                p.rise = 0f32;
+            }
+            p.age < p.style.lifetime
+        });
+    }
+
+    fn spawn(&mut self, emitter: &Emitter) -> Particle {
+        let style = emitter.style;
+        let angle = self.rng.next_f32() * TAU;
+        let speed = style.speed * (0.5 + self.rng.next_f32());
+        Particle {
+            position: emitter.position,
+            height: emitter.height,
+            velocity: Vec2::new(angle.cos(), angle.sin()).scaled(speed),
+            rise: style.rise * (0.5 + self.rng.next_f32()),
+            age: 0,
+            style,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Emitter, ParticleStyle, ParticleSystem};
+    use crate::Vec2;
+
+    #[test]
+    fn particles_fall_fade_and_expire() {
+        let mut system = ParticleSystem::new();
+        let style = ParticleStyle::SPARKS;
+        system.add_emitter(Emitter::new(Vec2::new(0.5, 0.5), 0.5, style).with_burst(4));
+        system.tick(|_| 0f32);
+        assert_eq!(system.particles().len(), 4);
+        assert!(system.emitters().is_empty());
+        let first = system.particles()[0];
+        assert!(first.height > 0.5 && first.position != Vec2::new(0.5, 0.5));
+        for _ in 1..style.lifetime / 2 {
+            system.tick(|_| 0f32);
+        }
+        let p = system.particles()[0];
+        assert!(p.rise < first.rise);
+        assert!(p.color().a < style.color.a);
+        for _ in style.lifetime / 2..style.lifetime {
+            system.tick(|_| 0f32);
+        }
+        assert!(system.particles().is_empty());
+
+        // endless emitters keep bursting at their interval
+        let smoke = Emitter::new(Vec2::new(0.5, 0.5), 0f32, ParticleStyle::SMOKE)
+            .with_burst(1)
+            .with_interval(5)
+            .with_bursts(None);
+        system.add_emitter(smoke);
+        for _ in 0..10 {
+            system.tick(|_| 0f32);
+        }
+        assert_eq!(system.particles().len(), 2);
+        assert_eq!(system.emitters().len(), 1);
+    }
+}
@@ -1,10 +1,8 @@
-use std::{sync::Mutex};
+use super::{Error, Rect, Vec2};
 
-use crate::console_log;
-
-use super::{Error, Vec2};
-
-#[derive(Clone, Copy, Debug)]
+/// RGBA8 color, laid out as 4 bytes so a `[Color]` can be handed over as raw RGBA data.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Color {
     pub r: u8,
     pub g: u8,
@@ -40,12 +38,26 @@ impl Color {
     pub const fn blue() -> Self {
         Self::new(0, 0, 255, 255)
     }
+
+    /// Linear interpolation towards `other`, `t` going from 0 (`self`) to 1 (`other`).
+    pub fn lerp(self, other: Color, t: f32) -> Self {
+        let t = t.clamp(0f32, 1f32);
+        let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+        Self::new(
+            mix(self.r, other.r),
+            mix(self.g, other.g),
+            mix(self.b, other.b),
+            mix(self.a, other.a),
+        )
+    }
 }
 
+#[derive(Clone, Debug, PartialEq)]
 pub struct PixelBuffer {
     pub data: Vec<Color>,
     pub width: usize,
     pub height: usize,
+    clip: Option<Rect<isize>>,
 }
 
 impl PixelBuffer {
@@ -54,7 +66,31 @@ impl PixelBuffer {
             data: vec![Color::new(0, 0, 0, 255); w * h],
             width: w,
             height: h,
+            clip: None,
+        }
+    }
+
+    /// Pixels as tightly packed RGBA8 bytes, `width * height * 4` long.
+    pub fn as_bytes(&self) -> &[u8] {
+        // SAFETY: `Color` is `repr(C)` made of 4 `u8`, so it has no padding and an
+        // alignment of 1.
+        unsafe { std::slice::from_raw_parts(self.data.as_ptr() as *const u8, self.data.len() * 4) }
+    }
+
+    /// Copies the pixels as RGBA8 into `out`, which must be exactly `width * height * 4`
+    /// bytes long.
+    pub fn write_rgba(&self, out: &mut [u8]) -> crate::Result<()> {
+        let bytes = self.as_bytes();
+        if out.len() != bytes.len() {
+            return Err(Error::InvalidImage(format!(
+                "{} bytes for a {}x{} RGBA image",
+                out.len(),
+                self.width,
+                self.height
+            )));
         }
+        out.copy_from_slice(bytes);
+        Ok(())
     }
 
     pub fn addr(&self, x: usize, y: usize) -> Option<usize> {
@@ -80,25 +116,196 @@ impl PixelBuffer {
         self.addr(x, y).and_then(|addr| self.data.get(addr))
     }
 
+    /// Area drawing primitives are clipped to, the whole buffer unless narrowed by
+    /// [`PixelBuffer::set_clip`].
+    pub fn clip(&self) -> Rect<isize> {
+        let full = Rect::new(
+            Vec2::new(0, 0),
+            Vec2::new(self.width as isize, self.height as isize),
+        );
+        match self.clip {
+            Some(clip) => clip
+                .intersection(&full)
+                .unwrap_or(Rect::new(Vec2::new(0, 0), Vec2::new(0, 0))),
+            None => full,
+        }
+    }
+
+    /// Restricts the drawing primitives to `clip` (end exclusive), `None` resets it to
+    /// the whole buffer. Returns the previous clip rect.
+    pub fn set_clip(&mut self, clip: Option<Rect<isize>>) -> Option<Rect<isize>> {
+        std::mem::replace(&mut self.clip, clip)
+    }
+
+    /// Sets a pixel if it lies within the clip rect.
+    pub fn plot(&mut self, x: isize, y: isize, c: Color) {
+        if self.clip().contains(Vec2::new(x, y)) {
+            self.data[y as usize * self.width + x as usize] = c;
+        }
+    }
+
+    /// Horizontal line from `x0` to `x1`, both included.
+    pub fn hline(&mut self, x0: isize, x1: isize, y: isize, c: Color) {
+        let clip = self.clip();
+        if y < clip.start().y || y >= clip.end().y {
+            return;
+        }
+        let (x0, x1) = (
+            x0.min(x1).max(clip.start().x),
+            x0.max(x1).min(clip.end().x - 1),
+        );
+        if x0 > x1 {
+            return;
+        }
+        let row = y as usize * self.width;
+        self.data[row + x0 as usize..=row + x1 as usize].fill(c);
+    }
+
+    /// Vertical line from `y0` to `y1`, both included.
+    pub fn vline(&mut self, x: isize, y0: isize, y1: isize, c: Color) {
+        let clip = self.clip();
+        if x < clip.start().x || x >= clip.end().x {
+            return;
+        }
+        let (y0, y1) = (
+            y0.min(y1).max(clip.start().y),
+            y0.max(y1).min(clip.end().y - 1),
+        );
+        for y in y0..=y1 {
+            self.data[y as usize * self.width + x as usize] = c;
+        }
+    }
+
+    /// Bresenham line, both ends included. Only the part crossing the clip rect is
+    /// walked, so far away end points are cheap.
     pub fn line(&mut self, start: Vec2<isize>, end: Vec2<isize>, color: Color) {
-        let mut cur = start.cast::<f32>();
-        // let mut step_id = 0;
-        // let limit = 10;
-        if start == end {
+        let clip = self.clip();
+        if clip.is_empty() {
+            return;
+        }
+        let (dx, dy) = (end.x as i64 - start.x as i64, end.y as i64 - start.y as i64);
+        let x_major = dx.abs() >= dy.abs();
+        // walk along the major axis, the minor one is rounded from the exact slope
+        let (major, minor, d_major, d_minor, lo, hi) = if x_major {
+            (start.x, start.y, dx, dy, clip.start().x, clip.end().x)
+        } else {
+            (start.y, start.x, dy, dx, clip.start().y, clip.end().y)
+        };
+        let len = d_major.abs();
+        let dir = d_major.signum();
+        // steps `i` whose major coordinate lies within the clip rect
+        let (a, b) = (
+            (lo as i64 - major as i64) * dir,
+            (hi as i64 - 1 - major as i64) * dir,
+        );
+        let (first, last) = (a.min(b).max(0), a.max(b).min(len));
+        for i in first..=last {
+            let offset = if len == 0 {
+                0
+            } else {
+                (2 * i * d_minor.abs() + len).div_euclid(2 * len) * d_minor.signum()
+            };
+            let (p, q) = (major as i64 + i * dir, minor as i64 + offset);
+            let (x, y) = if x_major { (p, q) } else { (q, p) };
+            self.plot(x as isize, y as isize, color);
+        }
+    }
+
+    /// Outline of `rect`, `end` being exclusive.
+    pub fn rect(&mut self, rect: Rect<isize>, c: Color) {
+        if rect.is_empty() {
+            return;
+        }
+        let (start, end) = (rect.start(), rect.end() - Vec2::new(1, 1));
+        self.hline(start.x, end.x, start.y, c);
+        self.hline(start.x, end.x, end.y, c);
+        self.vline(start.x, start.y, end.y, c);
+        self.vline(end.x, start.y, end.y, c);
+    }
+
+    pub fn fill_rect(&mut self, rect: Rect<isize>, c: Color) {
+        let Some(rect) = rect.intersection(&self.clip()) else {
+            return;
+        };
+        for y in rect.start().y..rect.end().y {
+            self.hline(rect.start().x, rect.end().x - 1, y, c);
+        }
+    }
+
+    /// Midpoint circle outline.
+    pub fn circle(&mut self, center: Vec2<isize>, radius: isize, c: Color) {
+        if radius < 0 {
+            return;
+        }
+        let (mut x, mut y, mut err) = (radius, 0, 1 - radius);
+        while x >= y {
+            for (px, py) in [
+                (x, y),
+                (y, x),
+                (-y, x),
+                (-x, y),
+                (-x, -y),
+                (-y, -x),
+                (y, -x),
+                (x, -y),
+            ] {
+                self.plot(center.x + px, center.y + py, c);
+            }
+            y += 1;
+            if err < 0 {
+                err += 2 * y + 1;
+            } else {
+                x -= 1;
+                err += 2 * (y - x) + 1;
+            }
+        }
+    }
+
+    /// Disc made of the pixels within `radius` of `center`.
+    pub fn fill_circle(&mut self, center: Vec2<isize>, radius: isize, c: Color) {
+        if radius < 0 {
             return;
         }
-        // while step_id < limit {
-        loop {
-            let raw_dir = (end.cast::<f32>() - cur).cast::<isize>();
-            let mut dir: Vec2<f32> = raw_dir.cast::<f32>();
-            let goal = dir.normalize();
-            // console_log!("line({}, {}):step #{} | cur = {}, raw_dir = {}, dir = {}, goal = {}", start, end, step_id, cur, raw_dir, dir, goal);
-            if goal <= 1f32 {
-                break;
+        for dy in -radius..=radius {
+            let half = ((radius * radius - dy * dy) as f64).sqrt() as isize;
+            self.hline(center.x - half, center.x + half, center.y + dy, c);
+        }
+    }
+
+    /// Closed outline through `points`.
+    pub fn polygon(&mut self, points: &[Vec2<isize>], c: Color) {
+        for (i, p) in points.iter().enumerate() {
+            self.line(*p, points[(i + 1) % points.len()], c);
+        }
+    }
+
+    /// Fills the pixels whose center lies inside the polygon (even-odd rule).
+    pub fn fill_polygon(&mut self, points: &[Vec2<isize>], c: Color) {
+        let clip = self.clip();
+        let Some(min_y) = points.iter().map(|p| p.y).min() else {
+            return;
+        };
+        let max_y = points.iter().map(|p| p.y).max().unwrap_or(min_y);
+        let mut crossings = vec![];
+        for y in min_y.max(clip.start().y)..max_y.min(clip.end().y) {
+            let yc = y as f32 + 0.5;
+            crossings.clear();
+            for (i, a) in points.iter().enumerate() {
+                let b = points[(i + 1) % points.len()];
+                let (ay, by) = (a.y as f32, b.y as f32);
+                if (ay <= yc) != (by <= yc) {
+                    crossings.push(a.x as f32 + (yc - ay) / (by - ay) * (b.x - a.x) as f32);
+                }
+            }
+            crossings.sort_by(f32::total_cmp);
+            for span in crossings.chunks_exact(2) {
+                // pixels whose center x + 0.5 lies in [span[0], span[1])
+                let x0 = (span[0] - 0.5).ceil() as isize;
+                let x1 = (span[1] - 0.5).ceil() as isize - 1;
+                if x0 <= x1 {
+                    self.hline(x0, x1, y, c);
+                }
             }
-            let _ = self.put(cur.x as usize, cur.y as usize, color);
-            cur = cur + dir;
-            // step_id += 1;
         }
     }
 
@@ -108,3 +315,81 @@ impl PixelBuffer {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Color, PixelBuffer};
+    use crate::{Rect, Vec2};
+
+    fn lit(buf: &PixelBuffer) -> Vec<(usize, usize)> {
+        let mut ret = vec![];
+        for y in 0..buf.height {
+            for x in 0..buf.width {
+                if buf.get(x, y) == Some(&Color::white()) {
+                    ret.push((x, y));
+                }
+            }
+        }
+        ret
+    }
+
+    #[test]
+    fn rgba_bytes() {
+        let mut buf = PixelBuffer::new(2, 1);
+        buf.plot(1, 0, Color::new(1, 2, 3, 4));
+        assert_eq!(buf.as_bytes(), &[0, 0, 0, 255, 1, 2, 3, 4]);
+        let mut out = [0u8; 8];
+        buf.write_rgba(&mut out).unwrap();
+        assert_eq!(out, [0, 0, 0, 255, 1, 2, 3, 4]);
+        assert!(buf.write_rgba(&mut [0u8; 4]).is_err());
+    }
+
+    #[test]
+    fn bresenham_line() {
+        let mut buf = PixelBuffer::new(8, 4);
+        buf.line(Vec2::new(0, 0), Vec2::new(6, 2), Color::white());
+        assert_eq!(
+            lit(&buf),
+            vec![(0, 0), (1, 0), (2, 1), (3, 1), (4, 1), (5, 2), (6, 2)]
+        );
+        // the same pixels when drawn backwards
+        let mut back = PixelBuffer::new(8, 4);
+        back.line(Vec2::new(6, 2), Vec2::new(0, 0), Color::white());
+        assert_eq!(lit(&back), lit(&buf));
+    }
+
+    #[test]
+    fn primitives_are_clipped() {
+        let mut buf = PixelBuffer::new(4, 4);
+        buf.line(
+            Vec2::new(-1000000, 1),
+            Vec2::new(1000000, 1),
+            Color::white(),
+        );
+        assert_eq!(lit(&buf), vec![(0, 1), (1, 1), (2, 1), (3, 1)]);
+
+        let mut buf = PixelBuffer::new(4, 4);
+        buf.set_clip(Some(Rect::new(Vec2::new(1, 1), Vec2::new(3, 3))));
+        buf.fill_circle(Vec2::new(1, 1), 10, Color::white());
+        assert_eq!(lit(&buf), vec![(1, 1), (2, 1), (1, 2), (2, 2)]);
+        buf.set_clip(None);
+        buf.fill_rect(
+            Rect::new(Vec2::new(-5, -5), Vec2::new(1, 1)),
+            Color::white(),
+        );
+        assert_eq!(buf.get(0, 0), Some(&Color::white()));
+    }
+
+    #[test]
+    fn fill_polygon_covers_pixel_centers() {
+        let mut buf = PixelBuffer::new(4, 4);
+        let square = [
+            Vec2::new(1, 1),
+            Vec2::new(3, 1),
+            Vec2::new(3, 3),
+            Vec2::new(1, 3),
+        ];
+        buf.fill_polygon(&square, Color::white());
+        assert_eq!(lit(&buf), vec![(1, 1), (2, 1), (1, 2), (2, 2)]);
+    }
+}
@@ -0,0 +1,228 @@
+use super::Vec2;
+
+/// Grid line crossed to enter a tile.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Side {
+    /// A vertical line, the tile was entered through its east or west face.
+    X,
+    /// A horizontal line, the tile was entered through its north or south face.
+    Y,
+}
+
+/// Side of a wall hit by a ray.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum WallFace {
+    North,
+    South,
+    East,
+    West,
+    /// Side of a thin or diagonal wall its normal points out of: the left one going from
+    /// its start to its end, in screen space.
+    Front,
+    Back,
+}
+
+impl WallFace {
+    /// Face of a tile entered through `side` by a ray along `dir`.
+    pub fn entered(side: Side, dir: Vec2) -> Self {
+        match side {
+            Side::X if dir.x > 0f32 => Self::West,
+            Side::X => Self::East,
+            Side::Y if dir.y > 0f32 => Self::North,
+            Side::Y => Self::South,
+        }
+    }
+
+    /// Unit vector pointing out of a tile through this face, `None` for segment faces.
+    pub fn normal(&self) -> Option<Vec2> {
+        match self {
+            Self::North => Some(Vec2::new(0f32, -1f32)),
+            Self::South => Some(Vec2::new(0f32, 1f32)),
+            Self::East => Some(Vec2::new(1f32, 0f32)),
+            Self::West => Some(Vec2::new(-1f32, 0f32)),
+            Self::Front | Self::Back => None,
+        }
+    }
+
+    /// Face of the segment from `a` to `b` seen by a ray along `dir`.
+    pub fn of_segment(a: Vec2, b: Vec2, dir: Vec2) -> Self {
+        let normal = Vec2::new(a.y - b.y, b.x - a.x);
+        if dir.dot(normal) > 0f32 {
+            Self::Back
+        } else {
+            Self::Front
+        }
+    }
+
+    /// Horizontal texture coordinate of world position `pos` on a tile face, or of the
+    /// position `segment_u` along a segment, left to right seen from outside.
+    pub fn u(&self, pos: Vec2, segment_u: f32) -> f32 {
+        let fract = |v: f32| v - v.floor();
+        match self {
+            Self::West => fract(pos.y),
+            Self::East => 1f32 - fract(pos.y),
+            Self::South => fract(pos.x),
+            Self::North => 1f32 - fract(pos.x),
+            Self::Front => segment_u,
+            Self::Back => 1f32 - segment_u,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DdaStep {
+    pub tile: Vec2<i32>,
+    /// Distance travelled to enter the tile, in multiples of the ray direction.
+    pub distance: f32,
+    pub side: Side,
+}
+
+/// Walks the tiles crossed by a ray in order (digital differential analyzer),
+/// the origin tile excluded.
+#[derive(Clone, Debug)]
+pub struct Dda {
+    tile: Vec2<i32>,
+    step: Vec2<i32>,
+    side_dist: Vec2,
+    delta: Vec2,
+    max_distance: f32,
+}
+
+impl Dda {
+    /// `dir` doesn't need to be normalized, distances are expressed in multiples of it.
+    pub fn new(origin: Vec2, dir: Vec2, max_distance: f32) -> Self {
+        let tile = origin.floor::<i32>();
+        let delta = Vec2::new(
+            if dir.x == 0f32 {
+                f32::INFINITY
+            } else {
+                (1f32 / dir.x).abs()
+            },
+            if dir.y == 0f32 {
+                f32::INFINITY
+            } else {
+                (1f32 / dir.y).abs()
+            },
+        );
+        let (step_x, side_x) = if dir.x < 0f32 {
+            (-1, (origin.x - tile.x as f32) * delta.x)
+        } else {
+            (1, (tile.x as f32 + 1f32 - origin.x) * delta.x)
+        };
+        let (step_y, side_y) = if dir.y < 0f32 {
+            (-1, (origin.y - tile.y as f32) * delta.y)
+        } else {
+            (1, (tile.y as f32 + 1f32 - origin.y) * delta.y)
+        };
+        Self {
+            tile,
+            step: Vec2::new(step_x, step_y),
+            side_dist: Vec2::new(side_x, side_y),
+            delta,
+            max_distance,
+        }
+    }
+}
+
+impl Iterator for Dda {
+    type Item = DdaStep;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (distance, side) = if self.side_dist.x < self.side_dist.y {
+            let d = self.side_dist.x;
+            self.side_dist.x += self.delta.x;
+            self.tile.x += self.step.x;
+            (d, Side::X)
+        } else {
+            let d = self.side_dist.y;
+            self.side_dist.y += self.delta.y;
+            self.tile.y += self.step.y;
+            (d, Side::Y)
+        };
+        if !distance.is_finite() || distance > self.max_distance {
+            return None;
+        }
+        Some(DdaStep {
+            tile: self.tile,
+            distance,
+            side,
+        })
+    }
+}
+
+/// Where a ray meets a segment.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SegmentHit {
+    /// Distance along the ray, in multiples of its direction.
+    pub distance: f32,
+    /// Position along the segment, from 0 at its start to 1 at its end.
+    pub u: f32,
+}
+
+/// Where a hitscan ray meets a wall, see [`Game::shoot_ray`](super::Game::shoot_ray).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WallHit {
+    pub tile: Vec2<i32>,
+    pub face: WallFace,
+    pub position: Vec2,
+    /// Distance from the start of the ray, in tiles.
+    pub distance: f32,
+    /// Texture coordinates of the hit on the face, `v` going down from its top.
+    pub u: f32,
+    pub v: f32,
+}
+
+/// Intersection of the ray from `origin` along `dir` with the segment from `a` to `b`,
+/// `None` when they don't meet in front of the origin.
+pub fn intersect_segment(origin: Vec2, dir: Vec2, a: Vec2, b: Vec2) -> Option<SegmentHit> {
+    let cross = |u: Vec2, v: Vec2| u.x * v.y - u.y * v.x;
+    let edge = b - a;
+    let denom = cross(dir, edge);
+    if denom == 0f32 {
+        return None;
+    }
+    let rel = a - origin;
+    let distance = cross(rel, edge) / denom;
+    let u = cross(rel, dir) / denom;
+    (distance > 0f32 && (0f32..=1f32).contains(&u)).then_some(SegmentHit { distance, u })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{intersect_segment, Dda, Side};
+    use crate::Vec2;
+
+    #[test]
+    fn walks_tiles_in_order() {
+        let steps = Dda::new(Vec2::new(0.5, 0.5), Vec2::new(1.0, 0.25), 3.0).collect::<Vec<_>>();
+        let tiles = steps
+            .iter()
+            .map(|s| (s.tile.x, s.tile.y))
+            .collect::<Vec<_>>();
+        assert_eq!(tiles, vec![(1, 0), (2, 0), (2, 1), (3, 1)]);
+        assert_eq!(steps[0].side, Side::X);
+        assert_eq!(steps[2].side, Side::Y);
+        assert!((steps[0].distance - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn segment_intersection() {
+        let origin = Vec2::new(0.5, 0.5);
+        let hit = intersect_segment(
+            origin,
+            Vec2::new(2.0, 0.0),
+            Vec2::new(2.0, 0.0),
+            Vec2::new(2.0, 2.0),
+        )
+        .unwrap();
+        assert!((hit.distance - 0.75).abs() < 1e-6);
+        assert!((hit.u - 0.25).abs() < 1e-6);
+        let behind = intersect_segment(
+            origin,
+            Vec2::new(-1.0, 0.0),
+            Vec2::new(2.0, 0.0),
+            Vec2::new(2.0, 2.0),
+        );
+        assert_eq!(behind, None);
+    }
+}
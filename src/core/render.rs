@@ -0,0 +1,1018 @@
+use std::{f32::consts::TAU, ops::Range};
+
+use super::{
+    BlendMode, Color, Dda, Decal, DoorLock, EntityKind, Flats, Game, PixelBuffer, Side, TextureId,
+    Tile, TileGrid, Vec2, WallFace, WallKind,
+};
+
+/// Farthest distance a view ray travels, in tiles.
+pub const MAX_VIEW_DISTANCE: f32 = 64.0;
+
+/// Flat colors of the 3D view.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ViewStyle {
+    pub ceiling: Color,
+    pub floor: Color,
+    pub wall: Color,
+    pub door: Color,
+    pub locked_door: Color,
+    pub enemy: Color,
+    pub pickup: Color,
+    pub decoration: Color,
+    /// Sides of raised floors.
+    pub step: Color,
+    /// See-through walls without a texture, blended over what is behind.
+    pub window: Color,
+    /// Bullet holes, without a decal texture.
+    pub decal: Color,
+}
+
+impl Default for ViewStyle {
+    fn default() -> Self {
+        // ceiling and floor of the original palette
+        Self {
+            ceiling: Color::new(56, 56, 56, 255),
+            floor: Color::new(112, 112, 112, 255),
+            wall: Color::new(0, 0, 168, 255),
+            door: Color::new(0, 168, 168, 255),
+            locked_door: Color::new(168, 168, 0, 255),
+            enemy: Color::new(168, 84, 0, 255),
+            pickup: Color::new(252, 252, 84, 255),
+            decoration: Color::new(0, 168, 0, 255),
+            step: Color::new(84, 84, 84, 255),
+            window: Color::new(84, 168, 252, 96),
+            decal: Color::new(28, 28, 28, 255),
+        }
+    }
+}
+
+/// What hides the sprites drawn after the walls in one screen column.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColumnOcclusion {
+    /// Distance of the first wall reaching the eye, everything behind it is hidden.
+    pub depth: f32,
+    /// Distances from which the screen rows from the given one down are hidden, by low
+    /// walls and floors. Rows only go up with the distance.
+    pub clips: Vec<(f32, isize)>,
+    /// See-through walls in front of `depth` not drawn yet, front to back, with the
+    /// first row hidden in front of them.
+    panes: Vec<(Face, isize)>,
+}
+
+impl Default for ColumnOcclusion {
+    fn default() -> Self {
+        Self {
+            depth: MAX_VIEW_DISTANCE,
+            clips: vec![],
+            panes: vec![],
+        }
+    }
+}
+
+impl ColumnOcclusion {
+    /// First screen row hidden at `depth`, `None` when nothing is in front.
+    pub fn clip_row(&self, depth: f32) -> Option<isize> {
+        self.clips
+            .iter()
+            .take_while(|(d, _)| *d < depth)
+            .last()
+            .map(|(_, row)| *row)
+    }
+
+    fn clip(&mut self, depth: f32, row: isize) {
+        if self.clips.last().is_none_or(|(_, last)| row < *last) {
+            self.clips.push((depth, row));
+        }
+    }
+}
+
+/// A wall side seen in one screen column.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Face {
+    distance: f32,
+    /// Heights of its top and bottom edges.
+    top: f32,
+    bottom: f32,
+    /// Blended over what is behind when not opaque.
+    color: Color,
+    /// Texture replacing `color`.
+    texture: Option<TextureId>,
+    /// Horizontal texture coordinate.
+    u: f32,
+    /// Tile and face holding the decals drawn over it.
+    decals: (Vec2<i32>, WallFace),
+    /// Darkening of its light level.
+    dark: f32,
+}
+
+/// Height of the ceiling, in tiles.
+pub const CEILING_HEIGHT: f32 = 1.0;
+
+/// Camera space of one frame: maps world positions to screen coordinates.
+///
+/// Heights are in tiles: floors are at 0 unless raised, ceilings at [`CEILING_HEIGHT`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Projection {
+    pub origin: Vec2,
+    /// Unit view direction.
+    pub dir: Vec2,
+    /// Half the projection plane at distance 1, pointing right.
+    pub plane: Vec2,
+    pub width: usize,
+    pub height: usize,
+    /// Screen pixels per world unit at distance 1.
+    pub focal: f32,
+    /// Height of the eye.
+    pub eye: f32,
+}
+
+impl Projection {
+    pub fn new(game: &Game, width: usize, height: usize) -> Self {
+        let dir = game.player.direction.normalized();
+        let plane_scale = game.camera.plane_scale();
+        let tan_half_v = (game.camera.vertical_fov().to_radians() * 0.5).tan();
+        Self {
+            origin: game.player.position,
+            dir,
+            plane: Vec2::new(-dir.y, dir.x).scaled(plane_scale),
+            width,
+            height,
+            focal: height as f32 * 0.5 / tan_half_v,
+            eye: game.eye_height(),
+        }
+    }
+
+    /// View ray through the center of screen column `x`, its length along `dir` is 1 so
+    /// distances along it are perpendicular (fisheye free) distances.
+    pub fn column_ray(&self, x: usize) -> Vec2 {
+        let camera_x = 2f32 * (x as f32 + 0.5) / self.width as f32 - 1f32;
+        self.dir + self.plane.scaled(camera_x)
+    }
+
+    pub fn horizon(&self) -> f32 {
+        self.height as f32 * 0.5
+    }
+
+    /// Screen row of something at `height`, `depth` away.
+    pub fn screen_y(&self, height: f32, depth: f32) -> f32 {
+        self.horizon() - (height - self.eye) * self.focal / depth
+    }
+
+    /// Distance at which screen row `y` sees a horizontal plane at `height`, `None`
+    /// when the row looks away from it.
+    pub fn row_distance(&self, y: usize, height: f32) -> Option<f32> {
+        let offset = self.horizon() - (y as f32 + 0.5);
+        let distance = (height - self.eye) * self.focal / offset;
+        (distance > 0f32 && distance.is_finite()).then_some(distance)
+    }
+
+    /// Camera space depth and screen column of a world position, `None` when behind.
+    pub fn project(&self, pos: Vec2) -> Option<(f32, f32)> {
+        let rel = pos - self.origin;
+        let depth = rel.dot(self.dir);
+        if depth <= 0.05 {
+            return None;
+        }
+        let side = rel.dot(self.plane) / self.plane.sq_magn();
+        Some((depth, self.horizon_x() * (1f32 + side / depth)))
+    }
+
+    fn horizon_x(&self) -> f32 {
+        self.width as f32 * 0.5
+    }
+}
+
+impl Game {
+    /// Color shaded by the distance fog, if any.
+    pub fn shade(&self, color: Color, distance: f32) -> Color {
+        match self.fog.as_ref() {
+            Some(fog) => fog.apply(color, distance),
+            None => color,
+        }
+    }
+
+    /// Draws the 3D view: floor, ceiling, walls, sprites then particles. Returns the depth
+    /// of the wall drawn in each column.
+    pub fn render_world(&self, buf: &mut PixelBuffer) -> Vec<f32> {
+        let proj = Projection::new(self, buf.width, buf.height);
+        self.render_flats(buf, &proj);
+        let mut columns = self.render_walls(buf, &proj);
+        self.render_sprites(buf, &proj, &mut columns);
+        self.render_particles(buf, &proj, &mut columns);
+        for (x, column) in columns.iter_mut().enumerate() {
+            self.render_panes(buf, &proj, x, column, 0f32);
+        }
+        columns.iter().map(|c| c.depth).collect()
+    }
+
+    /// Casts the floor and ceiling row by row: every pixel of a row is at the same
+    /// distance, so the world position steps linearly along it.
+    fn render_flats(&self, buf: &mut PixelBuffer, proj: &Projection) {
+        let style = &self.view_style;
+        let flats =
+            TileGrid::covering(self.flats.keys().copied(), Flats::default()).map(|mut grid| {
+                for (tile, flats) in &self.flats {
+                    grid.set(*tile, *flats);
+                }
+                grid
+            });
+        // horizontal sky coordinate of each column, scrolling with the view angle as the
+        // texture spans a full turn
+        let sky_u = match self.sky.is_some() || self.flats.values().any(|f| f.sky.is_some()) {
+            true => (0..buf.width)
+                .map(|x| {
+                    let ray = proj.column_ray(x);
+                    ray.y.atan2(ray.x) / TAU
+                })
+                .collect(),
+            false => vec![],
+        };
+        for y in 0..buf.height {
+            let ceiling = (y as f32 + 0.5) < proj.horizon();
+            let (color, height) = match ceiling {
+                true => (style.ceiling, CEILING_HEIGHT),
+                false => (style.floor, 0f32),
+            };
+            let distance = proj.row_distance(y, height);
+            let fog = self
+                .fog
+                .as_ref()
+                .map(|f| f.factor(distance.unwrap_or(MAX_VIEW_DISTANCE)))
+                .unwrap_or_default();
+            let shade = |c: Color| match self.fog.as_ref() {
+                Some(f) => f.apply_factor(c, fog),
+                None => c,
+            };
+            // the sky hangs from the top of the screen down to the horizon, unfogged
+            let sky_v = (y as f32 + 0.5) / proj.horizon();
+            let sky = |x: usize, sky: Option<TextureId>| {
+                let texture = self.texture(sky.filter(|_| ceiling)?)?;
+                Some(texture.sample(sky_u[x], sky_v))
+            };
+            let flat_color = shade(color);
+            let row = &mut buf.data[y * buf.width..(y + 1) * buf.width];
+            let (Some(flats), Some(distance)) = (flats.as_ref(), distance) else {
+                for (x, pixel) in row.iter_mut().enumerate() {
+                    *pixel = sky(x, self.sky).unwrap_or(flat_color);
+                }
+                continue;
+            };
+            let mut pos = proj.origin + proj.column_ray(0).scaled(distance);
+            let step = proj.plane.scaled(2f32 * distance / proj.width as f32);
+            for (x, pixel) in row.iter_mut().enumerate() {
+                let tile = Vec2::new(pos.x.floor() as i32, pos.y.floor() as i32);
+                let flats = flats.get(tile).copied().unwrap_or_default();
+                let texture = match ceiling {
+                    true => flats.ceiling,
+                    false => flats.floor,
+                };
+                *pixel = match (
+                    sky(x, self.sky_of(&flats)),
+                    texture.and_then(|id| self.texture(id)),
+                ) {
+                    (Some(sky), _) => sky,
+                    (None, Some(texture)) => shade(texture.sample(pos.x, pos.y)),
+                    (None, None) => flat_color,
+                };
+                pos += step;
+            }
+        }
+    }
+
+    /// Casts a ray per column front to back, drawing the sides of what rises above the
+    /// tile before and the tops below the eye. Rays go on past walls lower than the eye
+    /// or than the highest wall, as what is behind may show above them.
+    fn render_walls(&self, buf: &mut PixelBuffer, proj: &Projection) -> Vec<ColumnOcclusion> {
+        let mut columns = vec![ColumnOcclusion::default(); buf.width];
+        let Some(tiles) = self.tiles() else {
+            return columns;
+        };
+        let floors = self.floor_grid();
+        let floor_at = |tile| {
+            floors
+                .as_ref()
+                .and_then(|f| f.get(tile))
+                .copied()
+                .unwrap_or_default()
+        };
+        let highest = self
+            .walls
+            .iter()
+            .map(|w| floor_at(w.position) + w.height)
+            .chain(self.doors.iter().map(|d| floor_at(d.position) + 1f32))
+            .chain(self.floor_heights.values().copied())
+            .fold(CEILING_HEIGHT, f32::max);
+        for (x, column) in columns.iter_mut().enumerate() {
+            let mut ray = proj.column_ray(x);
+            // moved and turned by the portals the ray goes through, so that distances
+            // along it stay the ones from the eye
+            let mut origin = proj.origin;
+            let mut steps = Dda::new(origin, ray, MAX_VIEW_DISTANCE);
+            let mut travelled = 0f32;
+            let mut tile = proj.origin.floor::<i32>();
+            let mut top = self.column_top(&tiles, floors.as_ref(), tile);
+            // first screen row hidden by what was drawn so far, the ones below too
+            let mut bottom = buf.height as isize;
+            while let Some(mut step) = steps.next() {
+                step.distance += travelled;
+                // top of the tile the ray leaves, floors at 0 are drawn by the flats
+                let mut surface_row = bottom;
+                if top < proj.eye {
+                    let far = proj.screen_y(top, step.distance);
+                    surface_row = (far - 0.5).ceil().max(0f32) as isize;
+                    if top != 0f32 {
+                        let color = self.top_color(&tiles, tile);
+                        for y in surface_row..bottom {
+                            let Some(d) = proj.row_distance(y as usize, top) else {
+                                continue;
+                            };
+                            let pos = origin + ray.scaled(d);
+                            let color = match color {
+                                Some(c) => c,
+                                None => self.flat_color(tile, pos, false),
+                            };
+                            buf.plot(x as isize, y, self.shade(color, d));
+                        }
+                    }
+                }
+                // thin or diagonal wall inside it, over the surface behind it
+                let mut blocked = false;
+                if let Some((wall, hit)) = self.segment_hit(&tiles, tile, origin, ray) {
+                    let floor = floor_at(tile);
+                    let (a, b) = wall.segment().unwrap_or_default();
+                    let dark = match wall.kind {
+                        WallKind::Thin {
+                            vertical: false, ..
+                        }
+                        | WallKind::SeeThrough {
+                            vertical: false, ..
+                        } => 0.3,
+                        WallKind::Diagonal { .. } => 0.15,
+                        _ => 0f32,
+                    };
+                    // textures read left to right from both sides
+                    let side = WallFace::of_segment(a, b, ray);
+                    let see_through = matches!(wall.kind, WallKind::SeeThrough { .. });
+                    let face = Face {
+                        distance: hit.distance,
+                        top: floor + wall.height,
+                        bottom: floor,
+                        color: match see_through {
+                            true => self.view_style.window,
+                            false => self.view_style.wall,
+                        },
+                        texture: wall.texture,
+                        u: side.u(origin + ray.scaled(hit.distance), hit.u),
+                        decals: (tile, side),
+                        dark,
+                    };
+                    if see_through {
+                        // drawn back to front once what is behind is
+                        column.panes.push((face, bottom));
+                    } else {
+                        let row = self.render_face(buf, proj, x, &face, bottom);
+                        bottom = bottom.min(row);
+                        column.clip(hit.distance, bottom);
+                        if face.top > proj.eye {
+                            column.depth = column.depth.min(hit.distance);
+                        }
+                        blocked = face.top >= proj.eye && face.top >= highest;
+                    }
+                }
+                bottom = bottom.min(surface_row);
+                column.clip(step.distance, bottom);
+                if blocked {
+                    break;
+                }
+
+                let previous = top;
+                tile = step.tile;
+                top = self.column_top(&tiles, floors.as_ref(), tile);
+                let side = WallFace::entered(step.side, ray);
+                let portal = match tiles.get(tile) {
+                    Some(Tile::Wall(_)) => self.portal(tile, side),
+                    _ => None,
+                };
+                if let Some(portal) = portal {
+                    // the floor and ceiling seen through it, then the walls on the way
+                    let hit = origin + ray.scaled(step.distance);
+                    ray = portal.direction(ray);
+                    let exit = portal.point(hit) + portal.normal.scaled(1e-4);
+                    origin = exit - ray.scaled(step.distance);
+                    let y0 = proj.screen_y(top, step.distance).floor() as isize;
+                    let y1 = proj.screen_y(previous, step.distance).ceil() as isize;
+                    let rows = y0.max(0)..y1.min(bottom);
+                    self.render_portal_flats(buf, proj, x, (origin, ray), rows, step.distance);
+                    column.depth = column.depth.min(step.distance);
+                    steps = Dda::new(exit, ray, MAX_VIEW_DISTANCE - step.distance);
+                    travelled = step.distance;
+                    tile = portal.exit;
+                    top = self.column_top(&tiles, floors.as_ref(), tile);
+                    continue;
+                }
+                if top > previous {
+                    let hit = origin + ray.scaled(step.distance);
+                    // faces seen along y are darker, like the two light levels of the original
+                    let dark = match step.side {
+                        Side::X => 0f32,
+                        Side::Y => 0.3,
+                    };
+                    let texture = match tiles.get(tile) {
+                        Some(Tile::Wall(w)) => self.walls[*w].texture,
+                        _ => None,
+                    };
+                    let face = Face {
+                        distance: step.distance,
+                        top,
+                        bottom: previous,
+                        color: self.side_color(&tiles, tile),
+                        texture,
+                        u: side.u(hit, 0f32),
+                        decals: (tile, side),
+                        dark,
+                    };
+                    let row = self.render_face(buf, proj, x, &face, bottom);
+                    bottom = bottom.min(row);
+                    column.clip(step.distance, bottom);
+                }
+                if top > proj.eye {
+                    column.depth = column.depth.min(step.distance);
+                }
+                if (top >= proj.eye && top >= highest) || bottom <= 0 {
+                    break;
+                }
+            }
+        }
+        columns
+    }
+
+    /// Draws the floor, ceiling or sky seen in `rows` of column `x` through a portal
+    /// `distance` away, `ray` being the origin and direction of the view ray past it.
+    fn render_portal_flats(
+        &self,
+        buf: &mut PixelBuffer,
+        proj: &Projection,
+        x: usize,
+        ray: (Vec2, Vec2),
+        rows: Range<isize>,
+        distance: f32,
+    ) {
+        let (origin, dir) = ray;
+        for y in rows {
+            let ceiling = (y as f32 + 0.5) < proj.horizon();
+            let height = match ceiling {
+                true => CEILING_HEIGHT,
+                false => 0f32,
+            };
+            let Some(d) = proj
+                .row_distance(y as usize, height)
+                .filter(|d| *d > distance)
+            else {
+                continue;
+            };
+            let pos = origin + dir.scaled(d);
+            let tile = pos.floor::<i32>();
+            let sky = self
+                .sky_of(&self.flats.get(&tile).copied().unwrap_or_default())
+                .filter(|_| ceiling)
+                .and_then(|id| self.texture(id));
+            let color = match sky {
+                Some(sky) => {
+                    sky.sample(dir.y.atan2(dir.x) / TAU, (y as f32 + 0.5) / proj.horizon())
+                }
+                None => self.shade(self.flat_color(tile, pos, ceiling), d),
+            };
+            buf.plot(x as isize, y, color);
+        }
+    }
+
+    /// Draws `face` in column `x` above row `bottom`, returns the first row it covers.
+    fn render_face(
+        &self,
+        buf: &mut PixelBuffer,
+        proj: &Projection,
+        x: usize,
+        face: &Face,
+        bottom: isize,
+    ) -> isize {
+        let y0 = proj.screen_y(face.top, face.distance).floor() as isize;
+        let y1 = proj.screen_y(face.bottom, face.distance).ceil() as isize - 1;
+        let y1 = y1.min(bottom - 1);
+        let texture = face.texture.and_then(|id| self.texture(id));
+        let decals = self
+            .decals(face.decals.0, face.decals.1)
+            .into_iter()
+            .flatten()
+            .filter(|d| (d.u - face.u).abs() < Decal::SIZE / 2f32)
+            .collect::<Vec<_>>();
+        let darken = |c: Color| Color {
+            a: c.a,
+            ..c.lerp(Color::black(), face.dark)
+        };
+        let color = darken(face.color);
+        if texture.is_none() && color.a == 255 && decals.is_empty() {
+            if y0 <= y1 {
+                buf.vline(x as isize, y0, y1, self.shade(color, face.distance));
+            }
+            return y0;
+        }
+        let fog = self.fog.as_ref().map(|f| (f, f.factor(face.distance)));
+        for y in y0.max(0)..=y1.min(buf.height as isize - 1) {
+            // height seen through the center of the row, textures hang from the top
+            let height =
+                proj.eye + (proj.horizon() - (y as f32 + 0.5)) * face.distance / proj.focal;
+            let v = face.top - height;
+            let base = match texture {
+                Some(texture) => texture.sample(face.u, v),
+                None => face.color,
+            };
+            let color = decals
+                .iter()
+                .fold(base, |color, decal| match decal.local(face.u, v) {
+                    Some(pos) => self.decal_color(pos, color),
+                    None => color,
+                });
+            let color = darken(color);
+            let color = match fog {
+                Some((fog, factor)) => fog.apply_factor(color, factor),
+                None => color,
+            };
+            let i = y as usize * buf.width + x;
+            if let Some(c) = BlendMode::Alpha.blend(color, buf.data[i]) {
+                buf.data[i] = c;
+            }
+        }
+        y0
+    }
+
+    /// Color of a decal at `pos` inside it over `color`.
+    fn decal_color(&self, pos: Vec2, color: Color) -> Color {
+        match self.decal_texture.and_then(|id| self.texture(id)) {
+            Some(texture) => BlendMode::Alpha
+                .blend(texture.sample(pos.x, pos.y), color)
+                .unwrap_or(color),
+            None => self.view_style.decal,
+        }
+    }
+
+    /// Draws the see-through walls of `column` further than `depth`, back to front.
+    fn render_panes(
+        &self,
+        buf: &mut PixelBuffer,
+        proj: &Projection,
+        x: usize,
+        column: &mut ColumnOcclusion,
+        depth: f32,
+    ) {
+        while let Some((face, bottom)) = column.panes.last().copied() {
+            if face.distance <= depth {
+                break;
+            }
+            column.panes.pop();
+            self.render_face(buf, proj, x, &face, bottom);
+        }
+    }
+
+    /// Color of the sides of what stands on `tile`.
+    fn side_color(&self, tiles: &TileGrid<Tile>, tile: Vec2<i32>) -> Color {
+        match tiles.get(tile) {
+            Some(Tile::Wall(w)) if self.walls[*w].kind == WallKind::Basic => self.view_style.wall,
+            Some(Tile::Door(d)) => match self.doors[*d].lock {
+                DoorLock::None => self.view_style.door,
+                _ => self.view_style.locked_door,
+            },
+            _ => self.view_style.step,
+        }
+    }
+
+    /// Color of the top of what stands on `tile`, `None` for floors.
+    fn top_color(&self, tiles: &TileGrid<Tile>, tile: Vec2<i32>) -> Option<Color> {
+        match tiles.get(tile) {
+            Some(Tile::Wall(w)) if self.walls[*w].kind == WallKind::Basic => {
+                Some(self.view_style.wall)
+            }
+            Some(Tile::Door(d)) if !self.doors[*d].is_open() => Some(self.side_color(tiles, tile)),
+            _ => None,
+        }
+    }
+
+    /// Unshaded color of the floor or ceiling of `tile` at world position `pos`.
+    fn flat_color(&self, tile: Vec2<i32>, pos: Vec2, ceiling: bool) -> Color {
+        let flats = self.flats.get(&tile).copied().unwrap_or_default();
+        let (texture, color) = match ceiling {
+            true => (flats.ceiling, self.view_style.ceiling),
+            false => (flats.floor, self.view_style.floor),
+        };
+        match texture.and_then(|id| self.texture(id)) {
+            Some(texture) => texture.sample(pos.x, pos.y),
+            None => color,
+        }
+    }
+
+    fn render_sprites(
+        &self,
+        buf: &mut PixelBuffer,
+        proj: &Projection,
+        columns: &mut [ColumnOcclusion],
+    ) {
+        let style = &self.view_style;
+        let mut sprites = self
+            .entities
+            .iter()
+            .filter(|e| e.is_alive())
+            .filter_map(|e| {
+                let (depth, x) = proj.project(e.position)?;
+                let texture = match e.kind {
+                    EntityKind::Decoration { sprite, .. } => self.sprite_textures.get(&sprite),
+                    _ => None,
+                };
+                // width and height in tiles, standing on the floor
+                let (color, size) = match e.kind {
+                    _ if texture.is_some() => (style.decoration, Vec2::new(1.0, 1.0)),
+                    k if k.is_enemy() => (style.enemy, Vec2::new(0.5, 0.8)),
+                    EntityKind::Decoration { .. } => (style.decoration, Vec2::new(0.4, 0.7)),
+                    _ => (style.pickup, Vec2::new(0.3, 0.3)),
+                };
+                let floor = self.floor_height(e.position.floor::<i32>());
+                let texture = texture.and_then(|id| self.texture(*id));
+                Some((depth, x, floor, color, size, texture))
+            })
+            .collect::<Vec<_>>();
+        // back to front
+        sprites.sort_by(|a, b| b.0.total_cmp(&a.0));
+        for (depth, center, floor, color, size, texture) in sprites {
+            let half_width = size.x * 0.5 * proj.focal / depth;
+            let start = (center - half_width).round().max(0f32) as usize;
+            let end = ((center + half_width).round().max(0f32) as usize).min(buf.width);
+            let top_y = proj.screen_y(floor + size.y, depth);
+            let bottom_y = proj.screen_y(floor, depth);
+            let top = top_y.floor() as isize;
+            let bottom = bottom_y.ceil() as isize - 1;
+            let color = self.shade(color, depth);
+            for (x, column) in columns.iter_mut().enumerate().take(end).skip(start) {
+                if depth >= column.depth {
+                    continue;
+                }
+                self.render_panes(buf, proj, x, column, depth);
+                let bottom = column
+                    .clip_row(depth)
+                    .map_or(bottom, |row| bottom.min(row - 1));
+                let Some(texture) = texture else {
+                    if top <= bottom {
+                        buf.vline(x as isize, top, bottom, color);
+                    }
+                    continue;
+                };
+                // masked like the original sprites
+                let u = (x as f32 + 0.5 - (center - half_width)) / (2f32 * half_width);
+                for y in top.max(0)..=bottom.min(buf.height as isize - 1) {
+                    let v = (y as f32 + 0.5 - top_y) / (bottom_y - top_y);
+                    let texel = texture.sample(u.clamp(0f32, 0.999), v.clamp(0f32, 0.999));
+                    let i = y as usize * buf.width + x;
+                    if let Some(c) =
+                        BlendMode::AlphaKey.blend(self.shade(texel, depth), buf.data[i])
+                    {
+                        buf.data[i] = c;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Draws the particles as squares of at least a pixel, back to front over the
+    /// sprites, hidden by the walls in front of them.
+    fn render_particles(
+        &self,
+        buf: &mut PixelBuffer,
+        proj: &Projection,
+        columns: &mut [ColumnOcclusion],
+    ) {
+        let mut particles = self
+            .particles
+            .particles()
+            .iter()
+            .filter_map(|p| {
+                let (depth, x) = proj.project(p.position)?;
+                Some((depth, x, p))
+            })
+            .collect::<Vec<_>>();
+        particles.sort_by(|a, b| b.0.total_cmp(&a.0));
+        for (depth, center, particle) in particles {
+            let half_size = (particle.style.size * 0.5 * proj.focal / depth).max(0.5);
+            let middle = proj.screen_y(particle.height, depth);
+            let span = |center: f32| {
+                let start = (center - half_size).round() as isize;
+                let end = ((center + half_size).round() as isize).max(start + 1);
+                (start, end)
+            };
+            let (x0, x1) = span(center);
+            let (y0, y1) = span(middle);
+            let color = self.shade(particle.color(), depth);
+            for x in x0.max(0)..x1.min(buf.width as isize) {
+                let column = &mut columns[x as usize];
+                if depth >= column.depth {
+                    continue;
+                }
+                self.render_panes(buf, proj, x as usize, column, depth);
+                let bottom = column.clip_row(depth).map_or(y1, |row| y1.min(row));
+                for y in y0.max(0)..bottom.min(buf.height as isize) {
+                    let i = y as usize * buf.width + x as usize;
+                    if let Some(c) = BlendMode::Alpha.blend(color, buf.data[i]) {
+                        buf.data[i] = c;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        Color, Emitter, Entity, EntityKind, Flats, Fog, Game, ParticleStyle, PixelBuffer,
+        Projection, Vec2, Wall, WallFace, WallKind,
+    };
+
+    fn game() -> Game {
+        // facing east, a wall 3 tiles ahead
+        let walls = (-2..=2)
+            .map(|y| Wall::new(WallKind::Basic, Vec2::new(3, y)))
+            .collect::<Vec<_>>();
+        Game::new()
+            .with_player_position(Vec2::new(0.5, 0.5))
+            .with_player_direction(Vec2::new(1.0, 0.0))
+            .with_walls(&walls)
+            .with_minimap(None)
+    }
+
+    #[test]
+    fn walls_and_sprites_are_depth_sorted() {
+        let mut game = game();
+        game.entities.push(Entity::spawn(
+            EntityKind::Guard,
+            Vec2::new(2.0, 0.5),
+            game.difficulty(),
+        ));
+        game.entities.push(Entity::spawn(
+            EntityKind::Guard,
+            Vec2::new(5.0, 0.5),
+            game.difficulty(),
+        ));
+        let mut buf = PixelBuffer::new(40, 30);
+        let depths = game.render_world(&mut buf);
+        let style = game.view_style;
+        assert!((depths[20] - 2.5).abs() < 1e-4);
+        // the near guard hides the wall, the one behind the wall is hidden
+        assert_eq!(buf.get(20, 16), Some(&style.enemy));
+        assert_eq!(buf.get(10, 15), Some(&style.wall));
+        assert_eq!(buf.get(20, 0), Some(&style.ceiling));
+        assert_eq!(buf.get(20, 29), Some(&style.floor));
+    }
+
+    #[test]
+    fn fog_darkens_with_distance() {
+        let mut game = game();
+        let mut buf = PixelBuffer::new(40, 30);
+        game.render(0.0, &mut buf);
+        let flat = *buf.get(20, 15).unwrap();
+        game.fog = Some(Fog::default());
+        game.render(0.0, &mut buf);
+        let near = *buf.get(20, 15).unwrap();
+        assert_ne!(near, flat);
+        game.player.position = Vec2::new(-5.5, 0.5);
+        game.render(0.0, &mut buf);
+        let far = *buf.get(20, 15).unwrap();
+        assert!(far.b < near.b);
+    }
+
+    #[test]
+    fn textured_floor_and_ceiling() {
+        let mut game = game();
+        let mut checker = PixelBuffer::new(2, 2);
+        checker.fill(Color::red());
+        checker.plot(1, 0, Color::white());
+        checker.plot(0, 1, Color::white());
+        let id = game.add_texture(checker);
+        for x in 0..3 {
+            for y in -2..=2 {
+                let floor = Some(id).filter(|_| x == 1);
+                game.set_flats(
+                    Vec2::new(x, y),
+                    Flats {
+                        floor,
+                        ..Flats::default()
+                    },
+                );
+            }
+        }
+        let mut buf = PixelBuffer::new(40, 30);
+        game.render_world(&mut buf);
+        let style = game.view_style;
+        // the floor tile ahead shows the texture, nearer rows the flat color
+        let proj = Projection::new(&game, 40, 30);
+        let tile_row = |min: f32, max: f32| {
+            (15..30)
+                .find(|y| (min..max).contains(&proj.row_distance(*y, 0.0).unwrap()))
+                .unwrap()
+        };
+        let textured = *buf.get(20, tile_row(0.6, 1.4)).unwrap();
+        assert!(textured == Color::red() || textured == Color::white());
+        assert_eq!(buf.get(20, tile_row(1.5, 2.0)), Some(&style.floor));
+        assert_eq!(buf.get(20, 0), Some(&style.ceiling));
+    }
+
+    #[test]
+    fn taller_walls_show_over_low_ones() {
+        let mut game = game();
+        game.walls
+            .push(Wall::new(WallKind::Basic, Vec2::new(1, 0)).with_height(0.25));
+        for wall in game.walls.iter_mut().filter(|w| w.position.x == 3) {
+            wall.height = 2.0;
+        }
+        game.set_floor_height(Vec2::new(2, 0), 0.5);
+        let guard = Entity::spawn(EntityKind::Guard, Vec2::new(2.5, 0.5), game.difficulty());
+        game.entities.push(guard);
+        let mut buf = PixelBuffer::new(40, 30);
+        let depths = game.render_world(&mut buf);
+        let style = game.view_style;
+        // the low wall doesn't stop the ray, the platform and guard show above it
+        assert!((depths[20] - 2.5).abs() < 1e-4);
+        let column = (0..30)
+            .map(|y| *buf.get(20, y).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(column[29], style.wall);
+        assert!(column[15..].contains(&style.step));
+        assert!(column.contains(&style.enemy));
+        // the tall wall goes through the ceiling
+        assert_eq!(column[0], style.wall);
+    }
+
+    #[test]
+    fn thin_and_diagonal_walls() {
+        let mut game = game();
+        let mut red = PixelBuffer::new(1, 1);
+        red.fill(Color::red());
+        let texture = game.add_texture(red);
+        let thin = WallKind::Thin {
+            vertical: true,
+            offset: 0.5,
+        };
+        game.walls
+            .push(Wall::new(thin, Vec2::new(1, 0)).with_texture(texture));
+        let mut buf = PixelBuffer::new(40, 30);
+        let depths = game.render_world(&mut buf);
+        assert!((depths[20] - 1.0).abs() < 1e-4);
+        assert_eq!(buf.get(20, 15), Some(&Color::red()));
+
+        let diagonal = Wall::new(WallKind::Diagonal { flipped: false }, Vec2::new(1, 0));
+        assert!(diagonal.overlaps(Vec2::new(1.5, 0.5), 0.1));
+        assert!(!diagonal.overlaps(Vec2::new(1.8, 0.2), 0.1));
+        game.walls.pop();
+        game.walls.push(diagonal);
+        assert!(!game.is_blocked(Vec2::new(1.8, 0.2), 0.1));
+        assert!(game.is_blocked(Vec2::new(1.3, 0.4), 0.1));
+        // seen through the middle of the tile, along y = x
+        let depths = game.render_world(&mut buf);
+        assert!((depths[20] - 1.0).abs() < 0.05);
+        assert!(depths[0] < 1.0 && depths[39] > 1.0);
+    }
+
+    #[test]
+    fn see_through_walls_show_what_is_behind() {
+        let mut game = game();
+        // bars over the upper half, opaque red below
+        let mut bars = PixelBuffer::new(1, 2);
+        bars.plot(0, 0, Color::transparent());
+        bars.plot(0, 1, Color::red());
+        let texture = game.add_texture(bars);
+        let kind = WallKind::SeeThrough {
+            vertical: true,
+            offset: 0.5,
+        };
+        game.walls
+            .push(Wall::new(kind, Vec2::new(1, 0)).with_texture(texture));
+        game.entities.push(Entity::spawn(
+            EntityKind::Guard,
+            Vec2::new(2.0, 0.5),
+            game.difficulty(),
+        ));
+        let mut buf = PixelBuffer::new(40, 30);
+        let depths = game.render_world(&mut buf);
+        assert!((depths[20] - 2.5).abs() < 1e-4);
+        assert_eq!(buf.get(20, 13), Some(&game.view_style.enemy));
+        assert_eq!(buf.get(20, 17), Some(&Color::red()));
+
+        game.explore();
+        assert!(game.is_explored(Vec2::new(2, 0)));
+        assert!(game.is_blocked(Vec2::new(1.5, 0.5), 0.1));
+        game.walls.last_mut().unwrap().blocks_movement = false;
+        assert!(!game.is_blocked(Vec2::new(1.5, 0.5), 0.1));
+    }
+
+    #[test]
+    fn sky_scrolls_with_the_view() {
+        let mut game = game();
+        let mut sky = PixelBuffer::new(4, 1);
+        sky.plot(0, 0, Color::red());
+        sky.plot(1, 0, Color::white());
+        game.sky = Some(game.add_texture(sky));
+        let mut buf = PixelBuffer::new(40, 30);
+        game.render_world(&mut buf);
+        assert_eq!(buf.get(20, 0), Some(&Color::red()));
+        assert_eq!(buf.get(20, 29), Some(&game.view_style.floor));
+        // a quarter turn scrolls by a quarter of the texture
+        game.player.direction = Vec2::new(0.0, 1.0);
+        game.render_world(&mut buf);
+        assert_eq!(buf.get(20, 0), Some(&Color::white()));
+
+        // indoor again under ceiling textures, up to 2 tiles ahead
+        let ceiling = game.add_texture(PixelBuffer::new(1, 1));
+        for x in 0..2 {
+            let flats = Flats {
+                ceiling: Some(ceiling),
+                ..Flats::default()
+            };
+            game.set_flats(Vec2::new(x, 0), flats);
+        }
+        game.player.direction = Vec2::new(1.0, 0.0);
+        game.render_world(&mut buf);
+        assert_eq!(buf.get(20, 0), Some(&Color::black()));
+        assert_eq!(buf.get(20, 6), Some(&Color::red()));
+    }
+
+    #[test]
+    fn textured_decorations_are_masked() {
+        let mut game = game();
+        let mut lamp = PixelBuffer::new(1, 2);
+        lamp.plot(0, 0, Color::transparent());
+        lamp.plot(0, 1, Color::red());
+        let texture = game.add_texture(lamp);
+        game.set_sprite_texture(3, Some(texture));
+        let kind = EntityKind::Decoration {
+            sprite: 3,
+            blocking: false,
+        };
+        game.entities.push(Entity::new(kind, Vec2::new(2.0, 0.5)));
+        let mut buf = PixelBuffer::new(40, 30);
+        game.render_world(&mut buf);
+        assert_eq!(buf.get(20, 14), Some(&game.view_style.wall));
+        assert_eq!(buf.get(20, 16), Some(&Color::red()));
+    }
+
+    #[test]
+    fn particles_are_hidden_by_walls() {
+        let mut game = game();
+        let style = ParticleStyle {
+            color: Color::green(),
+            fade: false,
+            speed: 0.0,
+            rise: 0.0,
+            gravity: 0.0,
+            lifetime: 10,
+            size: 0.2,
+        };
+        for position in [Vec2::new(2.0, 0.5), Vec2::new(4.0, 1.5)] {
+            let emitter = Emitter::new(position, 0.5, style).with_burst(1);
+            game.particles.add_emitter(emitter);
+        }
+        game.particles.tick(|_| 0f32);
+        let mut buf = PixelBuffer::new(40, 30);
+        game.render_world(&mut buf);
+        assert_eq!(buf.get(20, 15), Some(&Color::green()));
+        assert_eq!(buf.get(20, 11), Some(&game.view_style.wall));
+        assert_eq!(buf.get(29, 15), Some(&game.view_style.wall));
+    }
+
+    #[test]
+    fn portals_show_the_linked_side() {
+        let mut game = game();
+        let mut red = PixelBuffer::new(1, 1);
+        red.plot(0, 0, Color::red());
+        let red = game.add_texture(red);
+        let mut green = PixelBuffer::new(1, 1);
+        green.plot(0, 0, Color::green());
+        let flats = Flats {
+            ceiling: Some(game.add_texture(green)),
+            ..Default::default()
+        };
+        game.set_flats(Vec2::new(21, 5), flats);
+        // the middle wall looks out of the east side of a wall far away, facing a red one
+        game.walls
+            .push(Wall::new(WallKind::Basic, Vec2::new(20, 5)));
+        game.walls
+            .push(Wall::new(WallKind::Basic, Vec2::new(23, 5)).with_texture(red));
+        game.add_portal(
+            (Vec2::new(3, 0), WallFace::West),
+            (Vec2::new(20, 5), WallFace::East),
+        )
+        .unwrap();
+        let mut buf = PixelBuffer::new(40, 30);
+        let depths = game.render_world(&mut buf);
+        assert!((depths[20] - 2.5).abs() < 1e-4);
+        assert_eq!(buf.get(20, 15), Some(&Color::red()));
+        // the red wall is further than the portal, its floor and ceiling show around it
+        assert_eq!(buf.get(20, 10), Some(&Color::green()));
+        assert_eq!(buf.get(20, 20), Some(&game.view_style.floor));
+        assert_eq!(buf.get(10, 15), Some(&game.view_style.wall));
+    }
+}
@@ -0,0 +1,152 @@
+use std::ops::Range;
+
+/// Table behind the original `US_RndT`, walked one entry per call.
+#[rustfmt::skip]
+const RND_TABLE: [u8; 256] = [
+      0,   8, 109, 220, 222, 241, 149, 107,  75, 248, 254, 140,  16,  66,
+     74,  21, 211,  47,  80, 242, 154,  27, 205, 128, 161,  89,  77,  36,
+     95, 110,  85,  48, 212, 140, 211, 249,  22,  79, 200,  50,  28, 188,
+     52, 140, 202, 120,  68, 145,  62,  70, 184, 190,  91, 197, 152, 224,
+    149, 104,  25, 178, 252, 182, 202, 182, 141, 197,   4,  81, 181, 242,
+    145,  42,  39, 227, 156, 198, 225, 193, 219,  93, 122, 175, 249,   0,
+    175, 143,  70, 239,  46, 246, 163,  53, 163, 109, 168, 135,   2, 235,
+     25,  92,  20, 145, 138,  77,  69, 166,  78, 176, 173, 212, 166, 113,
+     94, 161,  41,  50, 239,  49, 111, 164,  70,  60,   2,  37, 171,  75,
+    136, 156,  11,  56,  42, 146, 138, 229,  73, 146,  77,  61,  98, 196,
+    135, 106,  63, 197, 195,  86,  96, 203, 113, 101, 170, 247, 181, 113,
+     80, 250, 108,   7, 255, 237, 129, 226,  79, 107, 112, 166, 103, 241,
+     24, 223, 239, 120, 198,  58,  60,  82, 128,   3, 184,  66, 143, 224,
+    145, 224,  81, 206, 163,  45,  63,  90, 168, 114,  59,  33, 159,  95,
+     28, 139, 123,  98, 125, 196,  15,  70, 194, 253,  54,  14, 109, 226,
+     71,  17, 161,  93, 186,  87, 244, 138,  20,  52, 123, 251,  26,  36,
+     17,  46,  52, 231, 232,  76,  31, 221,  84,  37, 216, 165, 212, 106,
+    197, 242,  98,  43,  39, 175, 254, 145, 190,  84, 118, 222, 187, 136,
+    120, 163, 236, 249,
+];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RngMode {
+    /// SplitMix64 generator, full 64 bit state.
+    #[default]
+    Modern,
+    /// Reproduces the original 256 entries table, the seed picks the starting index.
+    Original,
+}
+
+/// Seeded generator owned by the game, its whole state is two integers so it can be
+/// saved and restored exactly.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Rng {
+    mode: RngMode,
+    seed: u64,
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self::with_mode(RngMode::Modern, seed)
+    }
+
+    pub fn original(seed: u64) -> Self {
+        Self::with_mode(RngMode::Original, seed)
+    }
+
+    pub fn with_mode(mode: RngMode, seed: u64) -> Self {
+        let state = match mode {
+            RngMode::Modern => seed,
+            RngMode::Original => seed & 0xff,
+        };
+        Self { mode, seed, state }
+    }
+
+    /// Rebuilds a generator from values returned by [`Rng::mode`], [`Rng::seed`] and [`Rng::state`].
+    pub fn from_state(mode: RngMode, seed: u64, state: u64) -> Self {
+        Self { mode, seed, state }
+    }
+
+    pub fn mode(&self) -> RngMode {
+        self.mode
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+
+    /// Equivalent of `US_RndT`, a value in `0..=255`.
+    pub fn next_u8(&mut self) -> u8 {
+        match self.mode {
+            RngMode::Modern => (self.next_u64() >> 56) as u8,
+            RngMode::Original => {
+                self.state = (self.state + 1) & 0xff;
+                RND_TABLE[self.state as usize]
+            }
+        }
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        match self.mode {
+            RngMode::Modern => (self.next_u64() >> 32) as u32,
+            RngMode::Original => u32::from_le_bytes([
+                self.next_u8(),
+                self.next_u8(),
+                self.next_u8(),
+                self.next_u8(),
+            ]),
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        match self.mode {
+            RngMode::Modern => {
+                self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+                let mut z = self.state;
+                z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+                z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+                z ^ (z >> 31)
+            }
+            RngMode::Original => (self.next_u32() as u64) << 32 | self.next_u32() as u64,
+        }
+    }
+
+    /// Uniform value in `[0, 1)`.
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u32() >> 8) as f32 / (1u32 << 24) as f32
+    }
+
+    /// Value in `range`, `range.start` when it is empty.
+    pub fn range(&mut self, range: Range<i32>) -> i32 {
+        if range.end <= range.start {
+            return range.start;
+        }
+        let len = (range.end as i64 - range.start as i64) as u64;
+        (range.start as i64 + (self.next_u32() as u64 % len) as i64) as i32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Rng, RND_TABLE};
+
+    #[test]
+    fn original_table_sequence() {
+        let mut rng = Rng::original(0);
+        let values = (0..256).map(|_| rng.next_u8()).collect::<Vec<_>>();
+        assert_eq!(values[..4], [8, 109, 220, 222]);
+        assert_eq!(values[255], RND_TABLE[0]);
+    }
+
+    #[test]
+    fn same_seed_same_sequence() {
+        let mut a = Rng::new(42);
+        let mut b = Rng::new(42);
+        a.next_u64();
+        let mut restored = Rng::from_state(a.mode(), a.seed(), a.state());
+        b.next_u64();
+        assert_eq!(restored.next_u64(), b.next_u64());
+        assert!((0..1000).all(|_| (3..7).contains(&a.range(3..7))));
+    }
+}
@@ -0,0 +1,755 @@
+use super::{
+    Difficulty, Door, DoorLock, Entity, EntityKind, Error, Flats, Game, LevelProgress, Player, Rng,
+    RngMode, Teleporter, TextureId, Vec2, Wall, WallFace, WallKind,
+};
+
+/// Leading bytes of every save.
+pub const SAVE_MAGIC: [u8; 4] = *b"WSAV";
+/// Current save format version, bump it whenever the layout changes.
+pub const SAVE_VERSION: u16 = 10;
+
+/// Little-endian binary writer used by save games.
+#[derive(Debug, Default)]
+pub struct SaveWriter {
+    data: Vec<u8>,
+}
+
+impl SaveWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.data
+    }
+
+    pub fn bytes(&mut self, v: &[u8]) {
+        self.data.extend_from_slice(v);
+    }
+
+    pub fn u8(&mut self, v: u8) {
+        self.data.push(v);
+    }
+
+    pub fn bool(&mut self, v: bool) {
+        self.u8(v as u8);
+    }
+
+    pub fn u16(&mut self, v: u16) {
+        self.bytes(&v.to_le_bytes());
+    }
+
+    pub fn u32(&mut self, v: u32) {
+        self.bytes(&v.to_le_bytes());
+    }
+
+    pub fn u64(&mut self, v: u64) {
+        self.bytes(&v.to_le_bytes());
+    }
+
+    pub fn i32(&mut self, v: i32) {
+        self.bytes(&v.to_le_bytes());
+    }
+
+    pub fn f32(&mut self, v: f32) {
+        self.bytes(&v.to_le_bytes());
+    }
+
+    pub fn vec2(&mut self, v: Vec2) {
+        self.f32(v.x);
+        self.f32(v.y);
+    }
+
+    pub fn vec2i(&mut self, v: Vec2<i32>) {
+        self.i32(v.x);
+        self.i32(v.y);
+    }
+
+    pub fn texture(&mut self, v: Option<TextureId>) {
+        self.bool(v.is_some());
+        self.u32(v.unwrap_or_default() as u32);
+    }
+
+    pub fn list<T: Persist>(&mut self, items: &[T]) {
+        self.u32(items.len() as u32);
+        for item in items {
+            item.save(self);
+        }
+    }
+}
+
+/// Reader counterpart of [`SaveWriter`], every read fails cleanly on truncated data.
+#[derive(Debug)]
+pub struct SaveReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    version: u16,
+}
+
+impl<'a> SaveReader<'a> {
+    pub fn new(data: &'a [u8], version: u16) -> Self {
+        Self {
+            data,
+            pos: 0,
+            version,
+        }
+    }
+
+    /// Format version of the data being read.
+    pub fn version(&self) -> u16 {
+        self.version
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.data.len() - self.pos
+    }
+
+    pub fn bytes(&mut self, len: usize) -> crate::Result<&'a [u8]> {
+        if len > self.remaining() {
+            return Err(Error::InvalidSave(format!(
+                "unexpected end of data at offset {}, {} more bytes needed",
+                self.pos,
+                len - self.remaining()
+            )));
+        }
+        let ret = &self.data[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(ret)
+    }
+
+    fn array<const N: usize>(&mut self) -> crate::Result<[u8; N]> {
+        let mut ret = [0u8; N];
+        ret.copy_from_slice(self.bytes(N)?);
+        Ok(ret)
+    }
+
+    pub fn u8(&mut self) -> crate::Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub fn bool(&mut self) -> crate::Result<bool> {
+        Ok(self.u8()? != 0)
+    }
+
+    pub fn u16(&mut self) -> crate::Result<u16> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    pub fn u32(&mut self) -> crate::Result<u32> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    pub fn u64(&mut self) -> crate::Result<u64> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+
+    pub fn i32(&mut self) -> crate::Result<i32> {
+        Ok(i32::from_le_bytes(self.array()?))
+    }
+
+    pub fn f32(&mut self) -> crate::Result<f32> {
+        Ok(f32::from_le_bytes(self.array()?))
+    }
+
+    pub fn vec2(&mut self) -> crate::Result<Vec2> {
+        Ok(Vec2::new(self.f32()?, self.f32()?))
+    }
+
+    pub fn vec2i(&mut self) -> crate::Result<Vec2<i32>> {
+        Ok(Vec2::new(self.i32()?, self.i32()?))
+    }
+
+    pub fn texture(&mut self) -> crate::Result<Option<TextureId>> {
+        let present = self.bool()?;
+        let id = self.u32()? as TextureId;
+        Ok(present.then_some(id))
+    }
+
+    pub fn list<T: Persist>(&mut self) -> crate::Result<Vec<T>> {
+        let len = self.u32()?;
+        let mut ret = vec![];
+        for _ in 0..len {
+            ret.push(T::load(self)?);
+        }
+        Ok(ret)
+    }
+
+    fn invalid<T>(&self, what: &str, value: impl std::fmt::Display) -> crate::Result<T> {
+        Err(Error::InvalidSave(format!(
+            "invalid {} '{}' before offset {}",
+            what, value, self.pos
+        )))
+    }
+}
+
+/// Types that can be written to and read back from a save.
+pub trait Persist: Sized {
+    fn save(&self, w: &mut SaveWriter);
+    fn load(r: &mut SaveReader) -> crate::Result<Self>;
+}
+
+impl Persist for Difficulty {
+    fn save(&self, w: &mut SaveWriter) {
+        w.u8(self.index());
+    }
+
+    fn load(r: &mut SaveReader) -> crate::Result<Self> {
+        let index = r.u8()?;
+        match Difficulty::from_index(index) {
+            Some(d) => Ok(d),
+            None => r.invalid("difficulty", index),
+        }
+    }
+}
+
+impl Persist for Rng {
+    fn save(&self, w: &mut SaveWriter) {
+        w.u8(match self.mode() {
+            RngMode::Modern => 0,
+            RngMode::Original => 1,
+        });
+        w.u64(self.seed());
+        w.u64(self.state());
+    }
+
+    fn load(r: &mut SaveReader) -> crate::Result<Self> {
+        let mode = match r.u8()? {
+            0 => RngMode::Modern,
+            1 => RngMode::Original,
+            tag => return r.invalid("rng mode", tag),
+        };
+        Ok(Rng::from_state(mode, r.u64()?, r.u64()?))
+    }
+}
+
+impl Persist for Player {
+    fn save(&self, w: &mut SaveWriter) {
+        w.vec2(self.position);
+        w.vec2(self.direction);
+        w.i32(self.health);
+        w.u16(self.refire_tics);
+    }
+
+    fn load(r: &mut SaveReader) -> crate::Result<Self> {
+        Ok(Self {
+            position: r.vec2()?,
+            direction: r.vec2()?,
+            health: r.i32()?,
+            refire_tics: match r.version() >= 9 {
+                true => r.u16()?,
+                false => 0,
+            },
+        })
+    }
+}
+
+impl Persist for Wall {
+    fn save(&self, w: &mut SaveWriter) {
+        match self.kind {
+            WallKind::Basic => w.u8(0),
+            WallKind::Thin { vertical, offset } => {
+                w.u8(1);
+                w.bool(vertical);
+                w.f32(offset);
+            }
+            WallKind::Diagonal { flipped } => {
+                w.u8(2);
+                w.bool(flipped);
+            }
+            WallKind::SeeThrough { vertical, offset } => {
+                w.u8(3);
+                w.bool(vertical);
+                w.f32(offset);
+            }
+        }
+        w.vec2i(self.position);
+        w.f32(self.height);
+        w.texture(self.texture);
+        w.bool(self.blocks_movement);
+        w.bool(self.blocks_sight);
+    }
+
+    fn load(r: &mut SaveReader) -> crate::Result<Self> {
+        let kind = match r.u8()? {
+            0 => WallKind::Basic,
+            1 => WallKind::Thin {
+                vertical: r.bool()?,
+                offset: r.f32()?,
+            },
+            2 => WallKind::Diagonal { flipped: r.bool()? },
+            3 => WallKind::SeeThrough {
+                vertical: r.bool()?,
+                offset: r.f32()?,
+            },
+            tag => return r.invalid("wall kind", tag),
+        };
+        let mut wall = Self::new(kind, r.vec2i()?);
+        if r.version() >= 5 {
+            wall.height = r.f32()?;
+        }
+        if r.version() >= 6 {
+            wall.texture = r.texture()?;
+        }
+        if r.version() >= 7 {
+            wall.blocks_movement = r.bool()?;
+            wall.blocks_sight = r.bool()?;
+        }
+        Ok(wall)
+    }
+}
+
+impl Persist for Door {
+    fn save(&self, w: &mut SaveWriter) {
+        w.vec2i(self.position);
+        w.bool(self.vertical);
+        w.u8(self.lock as u8);
+        w.f32(self.open);
+    }
+
+    fn load(r: &mut SaveReader) -> crate::Result<Self> {
+        let position = r.vec2i()?;
+        let vertical = r.bool()?;
+        let lock = match r.u8()? {
+            0 => DoorLock::None,
+            1 => DoorLock::Gold,
+            2 => DoorLock::Silver,
+            3 => DoorLock::Elevator,
+            tag => return r.invalid("door lock", tag),
+        };
+        let mut ret = Self::new(position, vertical, lock);
+        ret.open = r.f32()?;
+        Ok(ret)
+    }
+}
+
+impl Persist for EntityKind {
+    fn save(&self, w: &mut SaveWriter) {
+        let tag = match self {
+            Self::Guard => 0,
+            Self::Officer => 1,
+            Self::SS => 2,
+            Self::Dog => 3,
+            Self::Mutant => 4,
+            Self::Food => 5,
+            Self::FirstAid => 6,
+            Self::Clip => 7,
+            Self::GoldKey => 8,
+            Self::SilverKey => 9,
+            Self::Cross => 10,
+            Self::Chalice => 11,
+            Self::Chest => 12,
+            Self::Crown => 13,
+            Self::ExtraLife => 14,
+            Self::Decoration { .. } => 15,
+        };
+        w.u8(tag);
+        if let Self::Decoration { sprite, blocking } = self {
+            w.u16(*sprite);
+            w.bool(*blocking);
+        }
+    }
+
+    fn load(r: &mut SaveReader) -> crate::Result<Self> {
+        Ok(match r.u8()? {
+            0 => Self::Guard,
+            1 => Self::Officer,
+            2 => Self::SS,
+            3 => Self::Dog,
+            4 => Self::Mutant,
+            5 => Self::Food,
+            6 => Self::FirstAid,
+            7 => Self::Clip,
+            8 => Self::GoldKey,
+            9 => Self::SilverKey,
+            10 => Self::Cross,
+            11 => Self::Chalice,
+            12 => Self::Chest,
+            13 => Self::Crown,
+            14 => Self::ExtraLife,
+            15 => Self::Decoration {
+                sprite: r.u16()?,
+                blocking: r.bool()?,
+            },
+            tag => return r.invalid("entity kind", tag),
+        })
+    }
+}
+
+impl Persist for Entity {
+    fn save(&self, w: &mut SaveWriter) {
+        self.kind.save(w);
+        w.vec2(self.position);
+        w.vec2(self.direction);
+        w.i32(self.health);
+        w.bool(self.patrolling);
+        w.u16(self.reaction_tics);
+    }
+
+    fn load(r: &mut SaveReader) -> crate::Result<Self> {
+        Ok(Self {
+            kind: EntityKind::load(r)?,
+            position: r.vec2()?,
+            direction: r.vec2()?,
+            health: r.i32()?,
+            patrolling: r.bool()?,
+            reaction_tics: r.u16()?,
+        })
+    }
+}
+
+impl Persist for LevelProgress {
+    fn save(&self, w: &mut SaveWriter) {
+        w.u16(self.level);
+        w.u32(self.tics);
+        w.u16(self.kills);
+        w.u16(self.total_kills);
+        w.u16(self.treasures);
+        w.u16(self.total_treasures);
+    }
+
+    fn load(r: &mut SaveReader) -> crate::Result<Self> {
+        Ok(Self {
+            level: r.u16()?,
+            tics: r.u32()?,
+            kills: r.u16()?,
+            total_kills: r.u16()?,
+            treasures: r.u16()?,
+            total_treasures: r.u16()?,
+        })
+    }
+}
+
+impl Persist for Vec2<i32> {
+    fn save(&self, w: &mut SaveWriter) {
+        w.vec2i(*self);
+    }
+
+    fn load(r: &mut SaveReader) -> crate::Result<Self> {
+        r.vec2i()
+    }
+}
+
+impl Persist for (Vec2<i32>, Flats) {
+    fn save(&self, w: &mut SaveWriter) {
+        w.vec2i(self.0);
+        w.texture(self.1.floor);
+        w.texture(self.1.ceiling);
+        w.texture(self.1.sky);
+    }
+
+    fn load(r: &mut SaveReader) -> crate::Result<Self> {
+        let tile = r.vec2i()?;
+        let floor = r.texture()?;
+        let ceiling = r.texture()?;
+        let sky = match r.version() >= 8 {
+            true => r.texture()?,
+            false => None,
+        };
+        Ok((
+            tile,
+            Flats {
+                floor,
+                ceiling,
+                sky,
+            },
+        ))
+    }
+}
+
+impl Persist for (Vec2<i32>, f32) {
+    fn save(&self, w: &mut SaveWriter) {
+        w.vec2i(self.0);
+        w.f32(self.1);
+    }
+
+    fn load(r: &mut SaveReader) -> crate::Result<Self> {
+        Ok((r.vec2i()?, r.f32()?))
+    }
+}
+
+impl Persist for (Vec2<i32>, Teleporter) {
+    fn save(&self, w: &mut SaveWriter) {
+        w.vec2i(self.0);
+        w.vec2i(self.1.target);
+        w.bool(self.1.direction.is_some());
+        w.vec2(self.1.direction.unwrap_or_default());
+        w.bool(self.1.actors);
+    }
+
+    fn load(r: &mut SaveReader) -> crate::Result<Self> {
+        let tile = r.vec2i()?;
+        let target = r.vec2i()?;
+        let turns = r.bool()?;
+        let direction = r.vec2()?;
+        let teleporter = Teleporter::new(target)
+            .with_direction(turns.then_some(direction))
+            .with_actors(r.bool()?);
+        Ok((tile, teleporter))
+    }
+}
+
+impl Persist for (Vec2<i32>, WallFace) {
+    fn save(&self, w: &mut SaveWriter) {
+        w.vec2i(self.0);
+        w.u8(match self.1 {
+            WallFace::North => 0,
+            WallFace::South => 1,
+            WallFace::East => 2,
+            WallFace::West => 3,
+            WallFace::Front => 4,
+            WallFace::Back => 5,
+        });
+    }
+
+    fn load(r: &mut SaveReader) -> crate::Result<Self> {
+        let tile = r.vec2i()?;
+        let face = match r.u8()? {
+            0 => WallFace::North,
+            1 => WallFace::South,
+            2 => WallFace::East,
+            3 => WallFace::West,
+            4 => WallFace::Front,
+            5 => WallFace::Back,
+            tag => return r.invalid("wall face", tag),
+        };
+        Ok((tile, face))
+    }
+}
+
+impl Persist for Game {
+    fn save(&self, w: &mut SaveWriter) {
+        self.difficulty.save(w);
+        self.progress.save(w);
+        self.rng.save(w);
+        self.player.save(w);
+        w.list(&self.walls);
+        w.list(&self.doors);
+        w.list(&self.entities);
+        w.list(&self.explored.iter().copied().collect::<Vec<_>>());
+        w.list(&self.flats.iter().map(|(k, v)| (*k, *v)).collect::<Vec<_>>());
+        w.list(
+            &self
+                .floor_heights
+                .iter()
+                .map(|(k, v)| (*k, *v))
+                .collect::<Vec<_>>(),
+        );
+        w.texture(self.sky);
+        w.list(
+            &self
+                .teleporters
+                .iter()
+                .map(|(k, v)| (*k, *v))
+                .collect::<Vec<_>>(),
+        );
+        // each link is held both ways
+        w.list(
+            &self
+                .portals
+                .iter()
+                .flat_map(|(k, v)| [*k, *v])
+                .collect::<Vec<_>>(),
+        );
+    }
+
+    fn load(r: &mut SaveReader) -> crate::Result<Self> {
+        let mut ret = Game::new();
+        ret.difficulty = Difficulty::load(r)?;
+        ret.progress = LevelProgress::load(r)?;
+        if r.version() >= 2 {
+            ret.rng = Rng::load(r)?;
+        }
+        ret.player = Player::load(r)?;
+        ret.walls = r.list()?;
+        ret.doors = r.list()?;
+        ret.entities = r.list()?;
+        if r.version() >= 3 {
+            ret.explored = r.list()?.into_iter().collect();
+        }
+        if r.version() >= 4 {
+            ret.flats = r.list()?.into_iter().collect();
+        }
+        if r.version() >= 5 {
+            ret.floor_heights = r.list()?.into_iter().collect();
+        }
+        if r.version() >= 8 {
+            ret.sky = r.texture()?;
+        }
+        if r.version() >= 10 {
+            ret.teleporters = r.list()?.into_iter().collect();
+            let faces: Vec<(Vec2<i32>, WallFace)> = r.list()?;
+            ret.portals = faces
+                .chunks_exact(2)
+                .map(|link| (link[0], link[1]))
+                .collect();
+        }
+        Ok(ret)
+    }
+}
+
+impl Game {
+    /// Serializes the whole game state, prefixed by [`SAVE_MAGIC`] and [`SAVE_VERSION`].
+    pub fn save(&self) -> Vec<u8> {
+        let mut w = SaveWriter::new();
+        w.bytes(&SAVE_MAGIC);
+        w.u16(SAVE_VERSION);
+        Persist::save(self, &mut w);
+        w.into_bytes()
+    }
+
+    /// FNV-1a hash of the saved state, cheap way to compare two games.
+    pub fn checksum(&self) -> u32 {
+        self.save().iter().fold(0x811c9dc5u32, |hash, b| {
+            (hash ^ *b as u32).wrapping_mul(0x01000193)
+        })
+    }
+
+    /// Replaces the saved state with the one of a [`Game::save`] output, keeping what
+    /// saves don't hold: textures and view settings. Bullet holes and particles are cleared.
+    pub fn restore(&mut self, data: &[u8]) -> crate::Result<()> {
+        let loaded = Self::load(data)?;
+        self.difficulty = loaded.difficulty;
+        self.progress = loaded.progress;
+        self.rng = loaded.rng;
+        self.player = loaded.player;
+        self.walls = loaded.walls;
+        self.doors = loaded.doors;
+        self.entities = loaded.entities;
+        self.explored = loaded.explored;
+        self.flats = loaded.flats;
+        self.floor_heights = loaded.floor_heights;
+        self.sky = loaded.sky;
+        self.teleporters = loaded.teleporters;
+        self.portals = loaded.portals;
+        self.decals.clear();
+        self.particles.clear();
+        self.palette_effects.clear();
+        Ok(())
+    }
+
+    /// Restores a game from [`Game::save`] output.
+    /// Saves written by a newer version are rejected with [`Error::UnsupportedVersion`].
+    pub fn load(data: &[u8]) -> crate::Result<Self> {
+        let mut header = SaveReader::new(data, 0);
+        if header.bytes(SAVE_MAGIC.len())? != SAVE_MAGIC {
+            return Err(Error::InvalidSave("not a save game".to_string()));
+        }
+        let version = header.u16()?;
+        if version == 0 || version > SAVE_VERSION {
+            return Err(Error::UnsupportedVersion {
+                found: version,
+                supported: SAVE_VERSION,
+            });
+        }
+        let mut r = SaveReader::new(&data[data.len() - header.remaining()..], version);
+        let ret = <Game as Persist>::load(&mut r)?;
+        if r.remaining() != 0 {
+            return Err(Error::InvalidSave(format!(
+                "{} trailing bytes",
+                r.remaining()
+            )));
+        }
+        Ok(ret)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        Difficulty, Error, Flats, Game, Map, Player, Teleporter, Vec2, WallFace, WallKind,
+        SAVE_VERSION,
+    };
+
+    fn game() -> Game {
+        #[rustfmt::skip]
+        let tiles = vec![
+            1,  1, 1,
+            1,  0, 1,
+            1, 92, 1,
+        ];
+        #[rustfmt::skip]
+        let objects = vec![
+            0,  0,   0,
+            0, 19, 108,
+            0, 60,   0,
+        ];
+        let map = Map::new(3, 3, tiles, objects).unwrap();
+        Game::new()
+            .with_seed(1234)
+            .with_difficulty(Difficulty::Hard)
+            .with_map(&map)
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut game = game();
+        game.doors[0].open = 0.5;
+        game.hurt_player(10);
+        game.fire();
+        game.rng().next_u32();
+        game.explore();
+        game.set_flats(
+            Vec2::new(1, 1),
+            Flats {
+                floor: Some(3),
+                ceiling: None,
+                sky: Some(1),
+            },
+        );
+        game.set_floor_height(Vec2::new(2, 1), 0.25);
+        game.walls[0].height = 0.5;
+        game.walls[1].kind = WallKind::Thin {
+            vertical: true,
+            offset: 0.5,
+        };
+        game.walls[1].texture = Some(2);
+        game.walls[2].blocks_sight = false;
+        game.sky = Some(4);
+        let teleporter = Teleporter::new(Vec2::new(1, 1)).with_direction(Some(Vec2::new(0.0, 1.0)));
+        game.set_teleporter(Vec2::new(2, 2), Some(teleporter));
+        game.add_portal(
+            (Vec2::new(0, 0), WallFace::East),
+            (Vec2::new(2, 0), WallFace::South),
+        )
+        .unwrap();
+        let loaded = Game::load(&game.save()).unwrap();
+        assert_eq!(loaded.save(), game.save());
+        assert_eq!(loaded.difficulty, Difficulty::Hard);
+        assert_eq!(loaded.walls.len(), 7);
+        assert_eq!(loaded.walls[0].height, 0.5);
+        assert_eq!(loaded.walls[1].kind, game.walls[1].kind);
+        assert_eq!(loaded.walls[1].texture, Some(2));
+        assert!(!loaded.walls[2].blocks_sight && loaded.walls[2].blocks_movement);
+        assert_eq!(loaded.doors, game.doors);
+        assert_eq!(loaded.entities.len(), 2);
+        assert_eq!(loaded.player.health, game.player.health);
+        assert_eq!(loaded.player.refire_tics, Player::REFIRE_TICS);
+        assert_eq!(loaded.teleporters, game.teleporters);
+        assert_eq!(loaded.portals, game.portals);
+        assert_eq!(loaded.rng, game.rng);
+        assert!(!loaded.explored.is_empty());
+        assert_eq!(loaded.explored, game.explored);
+        assert_eq!(loaded.flats, game.flats);
+        assert_eq!(loaded.floor_heights, game.floor_heights);
+        assert_eq!(loaded.sky, game.sky);
+    }
+
+    #[test]
+    fn newer_version_is_rejected() {
+        let mut data = game().save();
+        data[4..6].copy_from_slice(&(SAVE_VERSION + 1).to_le_bytes());
+        assert!(matches!(
+            Game::load(&data),
+            Err(Error::UnsupportedVersion { found, .. }) if found == SAVE_VERSION + 1
+        ));
+    }
+
+    #[test]
+    fn truncated_save_is_rejected() {
+        let data = game().save();
+        assert!(matches!(
+            Game::load(&data[..data.len() - 1]),
+            Err(Error::InvalidSave(..))
+        ));
+    }
+}
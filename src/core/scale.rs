@@ -0,0 +1,191 @@
+use super::{Blit, Color, Game, IndexedBuffer, PixelBuffer, Rect, Vec2};
+
+/// How a lower resolution frame is fitted into the output.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ScaleMode {
+    /// Fills the whole output, distorting the image when the aspect ratios differ.
+    Stretch,
+    /// Largest size keeping the aspect ratio, bars fill the rest.
+    #[default]
+    Letterbox,
+    /// Largest whole multiple of the size, so every pixel is scaled the same. Falls back
+    /// to `Letterbox` when the output is smaller than the frame.
+    Integer,
+}
+
+impl ScaleMode {
+    /// Area of a `dst` sized output covered by a `src` sized frame.
+    pub fn fit(&self, src: Vec2<usize>, dst: Vec2<usize>) -> Rect<isize> {
+        let size = match self {
+            _ if src.x == 0 || src.y == 0 => Vec2::new(0, 0),
+            Self::Stretch => dst,
+            Self::Integer if dst.x >= src.x && dst.y >= src.y => {
+                let factor = (dst.x / src.x).min(dst.y / src.y);
+                Vec2::new(src.x * factor, src.y * factor)
+            }
+            Self::Letterbox | Self::Integer => {
+                if dst.x * src.y <= dst.y * src.x {
+                    Vec2::new(dst.x, src.y * dst.x / src.x)
+                } else {
+                    Vec2::new(src.x * dst.y / src.y, dst.y)
+                }
+            }
+        };
+        let start = Vec2::new((dst.x - size.x) / 2, (dst.y - size.y) / 2).cast::<isize>();
+        Rect::new(start, start + size.cast::<isize>())
+    }
+}
+
+/// Internal resolution the world is rendered at before being upscaled to the output.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RenderScale {
+    /// `None` renders directly at the output resolution.
+    pub resolution: Option<Vec2<usize>>,
+    pub mode: ScaleMode,
+    /// Color of the bars around a letterboxed frame.
+    pub background: Color,
+}
+
+impl Default for RenderScale {
+    fn default() -> Self {
+        Self {
+            resolution: None,
+            mode: ScaleMode::default(),
+            background: Color::black(),
+        }
+    }
+}
+
+impl RenderScale {
+    /// The original 320x200 resolution.
+    pub fn original() -> Self {
+        Self {
+            resolution: Some(Vec2::new(320, 200)),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_mode(mut self, mode: ScaleMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Size of the area showing the world in a `output` sized target.
+    pub fn viewport(&self, output: Vec2<usize>) -> Vec2<usize> {
+        match (self.resolution, self.mode) {
+            (Some(_), ScaleMode::Stretch) | (None, _) => output,
+            (Some(resolution), _) => resolution,
+        }
+    }
+}
+
+impl PixelBuffer {
+    /// Nearest neighbour scales the whole buffer into `out`.
+    pub fn upscale_into(&self, out: &mut PixelBuffer, mode: ScaleMode, background: Color) {
+        let area = mode.fit(
+            Vec2::new(self.width, self.height),
+            Vec2::new(out.width, out.height),
+        );
+        if area.start() != Vec2::new(0, 0) {
+            out.fill(background);
+        }
+        out.blit(
+            self,
+            &Blit::new(area.start()).with_size(area.area().cast::<usize>()),
+        );
+    }
+}
+
+impl Game {
+    /// Renders at the internal resolution of `render_scale` into `scene`, reallocated
+    /// when needed, then upscales the result into `out`. In the 256 color mode the frame
+    /// goes through `indexed` on the way.
+    pub fn render_scaled(
+        &self,
+        dt: f32,
+        scene: &mut PixelBuffer,
+        indexed: &mut IndexedBuffer,
+        out: &mut PixelBuffer,
+    ) {
+        let Some(resolution) = self.render_scale.resolution else {
+            self.render(dt, out);
+            self.through_palette(indexed, out);
+            return;
+        };
+        if scene.width != resolution.x || scene.height != resolution.y {
+            *scene = PixelBuffer::new(resolution.x, resolution.y);
+        }
+        self.render(dt, scene);
+        self.through_palette(indexed, scene);
+        scene.upscale_into(out, self.render_scale.mode, self.render_scale.background);
+    }
+
+    /// In the 256 color mode, turns `frame` into palette indices and presents them back.
+    fn through_palette(&self, indexed: &mut IndexedBuffer, frame: &mut PixelBuffer) {
+        if let Some(map) = self.indexed.as_ref() {
+            indexed.quantize(frame, map);
+            self.present_indexed(indexed, map.palette(), frame);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ScaleMode;
+    use crate::{Color, ColorMap, Game, IndexedBuffer, Palette, PixelBuffer, Rect, Vec2};
+
+    #[test]
+    fn fit_modes() {
+        let src = Vec2::new(320, 200);
+        let rect = |start: (isize, isize), end: (isize, isize)| {
+            Rect::new(Vec2::new(start.0, start.1), Vec2::new(end.0, end.1))
+        };
+        let dst = Vec2::new(1024, 768);
+        assert_eq!(ScaleMode::Stretch.fit(src, dst), rect((0, 0), (1024, 768)));
+        assert_eq!(
+            ScaleMode::Letterbox.fit(src, dst),
+            rect((0, 64), (1024, 704))
+        );
+        assert_eq!(ScaleMode::Integer.fit(src, dst), rect((32, 84), (992, 684)));
+        assert_eq!(
+            ScaleMode::Integer.fit(src, Vec2::new(160, 200)),
+            rect((0, 50), (160, 150))
+        );
+    }
+
+    #[test]
+    fn upscale_letterboxed() {
+        let mut frame = PixelBuffer::new(2, 1);
+        frame.plot(1, 0, Color::white());
+        let mut out = PixelBuffer::new(4, 4);
+        out.fill(Color::red());
+        frame.upscale_into(&mut out, ScaleMode::Letterbox, Color::blue());
+        let row = |y| (0..4).map(|x| *out.get(x, y).unwrap()).collect::<Vec<_>>();
+        assert_eq!(row(0), vec![Color::blue(); 4]);
+        let expected = [
+            Color::black(),
+            Color::black(),
+            Color::white(),
+            Color::white(),
+        ];
+        assert_eq!(row(1), expected);
+        assert_eq!(row(2), expected);
+        assert_eq!(row(3), vec![Color::blue(); 4]);
+    }
+
+    #[test]
+    fn indexed_mode_shifts_the_palette() {
+        let mut game = Game::new();
+        game.indexed = Some(ColorMap::new(Palette::grayscale()));
+        game.resize(8, 8);
+        game.palette_effects.damage(5);
+        let mut scene = PixelBuffer::new(0, 0);
+        let mut indexed = IndexedBuffer::new(0, 0);
+        let mut out = PixelBuffer::new(8, 8);
+        game.render_scaled(0.0, &mut scene, &mut indexed, &mut out);
+        assert_eq!((indexed.width, indexed.height), (8, 8));
+        let shifted = game.palette_effects.apply(&Palette::grayscale());
+        assert!(out.data.iter().all(|c| shifted.colors.contains(c)));
+        assert!(out.data.iter().any(|c| c.r > c.g));
+    }
+}
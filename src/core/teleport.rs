@@ -0,0 +1,265 @@
+use super::{Entity, Error, Game, Player, Vec2, WallFace};
+
+/// Tile sending whoever walks into it to another one.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Teleporter {
+    pub target: Vec2<i32>,
+    /// Facing on arrival, `None` keeps the current one.
+    pub direction: Option<Vec2>,
+    /// Also sends the actors moved with [`Game::move_entity`], not only the player.
+    pub actors: bool,
+}
+
+impl Teleporter {
+    pub fn new(target: Vec2<i32>) -> Self {
+        Self {
+            target,
+            direction: None,
+            actors: false,
+        }
+    }
+
+    pub fn with_direction(mut self, direction: Option<Vec2>) -> Self {
+        self.direction = direction;
+        self
+    }
+
+    pub fn with_actors(mut self, actors: bool) -> Self {
+        self.actors = actors;
+        self
+    }
+}
+
+/// Rigid motion taking what enters a wall face with a portal out of the face it is
+/// linked to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PortalTransform {
+    /// Centers of the entry and exit faces.
+    from: Vec2,
+    to: Vec2,
+    /// Cosine and sine of the rotation.
+    rotation: Vec2,
+    /// Unit vector pointing out of the exit face.
+    pub normal: Vec2,
+    /// Tile in front of the exit face.
+    pub exit: Vec2<i32>,
+}
+
+impl PortalTransform {
+    fn new(entry: (Vec2<i32>, WallFace), exit: (Vec2<i32>, WallFace)) -> Option<Self> {
+        let center = |(tile, face): (Vec2<i32>, WallFace)| {
+            let normal = face.normal()?;
+            Some((
+                tile.cast::<f32>() + Vec2::new(0.5, 0.5) + normal.scaled(0.5),
+                normal,
+            ))
+        };
+        let (from, inward) = center(entry)?;
+        let (to, normal) = center(exit)?;
+        // turns the way into the entry face into the way out of the exit one
+        let inward = inward.scaled(-1f32);
+        let rotation = Vec2::new(
+            normal.x * inward.x + normal.y * inward.y,
+            normal.y * inward.x - normal.x * inward.y,
+        );
+        Some(Self {
+            from,
+            to,
+            rotation,
+            normal,
+            exit: exit.0 + normal.cast::<i32>(),
+        })
+    }
+
+    pub fn direction(&self, dir: Vec2) -> Vec2 {
+        let r = self.rotation;
+        Vec2::new(r.x * dir.x - r.y * dir.y, r.x * dir.y + r.y * dir.x)
+    }
+
+    pub fn point(&self, pos: Vec2) -> Vec2 {
+        self.to + self.direction(pos - self.from)
+    }
+}
+
+impl Game {
+    pub fn set_teleporter(&mut self, tile: Vec2<i32>, teleporter: Option<Teleporter>) {
+        match teleporter {
+            Some(teleporter) => self.teleporters.insert(tile, teleporter),
+            None => self.teleporters.remove(&tile),
+        };
+    }
+
+    /// Links two wall faces both ways: the view and the player going into one come out
+    /// of the other. Only the sides of whole tiles can be linked.
+    ///
+    /// The walls keep blocking movement on their other sides, the player only walks
+    /// through the linked faces when it fits on the other side. The view through a portal
+    /// shows walls, floors and ceilings, but no sprites, and is not clipped to it: what
+    /// shows through should not rise above it.
+    pub fn add_portal(
+        &mut self,
+        a: (Vec2<i32>, WallFace),
+        b: (Vec2<i32>, WallFace),
+    ) -> crate::Result<()> {
+        if a.1.normal().is_none() || b.1.normal().is_none() {
+            return Err(Error::InvalidMap(
+                "portals only link the sides of whole tiles".to_string(),
+            ));
+        }
+        self.remove_portal(a);
+        self.remove_portal(b);
+        self.portals.insert(a, b);
+        self.portals.insert(b, a);
+        Ok(())
+    }
+
+    /// Removes the portal of a wall face and the one it is linked to.
+    pub fn remove_portal(&mut self, face: (Vec2<i32>, WallFace)) {
+        if let Some(linked) = self.portals.remove(&face) {
+            self.portals.remove(&linked);
+        }
+    }
+
+    /// Transform through the portal on `face` of `tile`, `None` when it has none.
+    pub fn portal(&self, tile: Vec2<i32>, face: WallFace) -> Option<PortalTransform> {
+        let linked = self.portals.get(&(tile, face))?;
+        PortalTransform::new((tile, face), *linked)
+    }
+
+    /// Portal crossed by a move from `from` to `to`, through the side of the tile `to` is
+    /// in.
+    pub fn portal_crossed(&self, from: Vec2, to: Vec2) -> Option<PortalTransform> {
+        let tile = to.floor::<i32>();
+        self.portal(tile, face_towards(tile, from.floor::<i32>())?)
+    }
+
+    /// Whether the player is blocked at `pos`, see [`Game::is_blocked`]. A wall with a
+    /// portal on the side facing the player's tile lets it in up to the portal, where
+    /// [`Game::move_player`] sends it through.
+    pub fn is_player_blocked(&self, pos: Vec2) -> bool {
+        let tile = pos.floor::<i32>();
+        self.is_blocked_except(pos, Player::RADIUS, |wall| {
+            face_towards(wall, tile).is_some_and(|face| self.portals.contains_key(&(wall, face)))
+        })
+    }
+
+    /// Teleporter sending whoever goes from tile `from` to `to`, `None` when staying in
+    /// the same tile.
+    fn teleporter_entered(&self, from: Vec2<i32>, to: Vec2<i32>) -> Option<Teleporter> {
+        self.teleporters.get(&to).filter(|_| from != to).copied()
+    }
+
+    /// Sends the player through the teleporter of the tile it just entered from `from`.
+    pub fn teleport_player(&mut self, from: Vec2<i32>) {
+        let tile = self.player.position.floor::<i32>();
+        if let Some(teleporter) = self.teleporter_entered(from, tile) {
+            self.player.position = teleporter.target.cast::<f32>() + Vec2::new(0.5, 0.5);
+            if let Some(dir) = teleporter.direction {
+                self.player.direction = dir.normalized();
+            }
+        }
+    }
+
+    /// Moves an entity by `delta` unless a wall or closed door is in the way, through the
+    /// teleporters taking actors.
+    pub fn move_entity(&mut self, index: usize, delta: Vec2) {
+        let from = self.entities[index].position;
+        let to = from + delta;
+        if self.is_blocked(to, Entity::RADIUS) {
+            return;
+        }
+        let teleporter = self
+            .teleporter_entered(from.floor::<i32>(), to.floor::<i32>())
+            .filter(|t| t.actors);
+        let entity = &mut self.entities[index];
+        entity.position = to;
+        if let Some(teleporter) = teleporter {
+            entity.position = teleporter.target.cast::<f32>() + Vec2::new(0.5, 0.5);
+            if let Some(dir) = teleporter.direction {
+                entity.direction = dir.normalized();
+            }
+        }
+    }
+}
+
+/// Face of the `wall` tile on the side of the neighbouring tile `from`, `None` when they
+/// don't share a side.
+fn face_towards(wall: Vec2<i32>, from: Vec2<i32>) -> Option<WallFace> {
+    match from - wall {
+        Vec2 { x: -1, y: 0 } => Some(WallFace::West),
+        Vec2 { x: 1, y: 0 } => Some(WallFace::East),
+        Vec2 { x: 0, y: -1 } => Some(WallFace::North),
+        Vec2 { x: 0, y: 1 } => Some(WallFace::South),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Teleporter;
+    use crate::{Entity, EntityKind, Game, Vec2, Wall, WallFace, WallKind};
+
+    #[test]
+    fn teleporters_and_portals_move_the_player() {
+        let mut game = Game::new()
+            .with_player_position(Vec2::new(0.5, 0.5))
+            .with_player_direction(Vec2::new(1.0, 0.0));
+        let teleporter =
+            Teleporter::new(Vec2::new(5, 5)).with_direction(Some(Vec2::new(0.0, -1.0)));
+        game.set_teleporter(Vec2::new(1, 0), Some(teleporter));
+        game.move_player(Vec2::new(0.6, 0.0));
+        assert_eq!(game.player.position, Vec2::new(5.5, 5.5));
+        assert_eq!(game.player.direction, Vec2::new(0.0, -1.0));
+
+        // actors only go through when the teleporter takes them
+        game.entities
+            .push(Entity::new(EntityKind::Guard, Vec2::new(0.5, 0.5)));
+        game.move_entity(0, Vec2::new(0.6, 0.0));
+        assert_eq!(game.entities[0].position, Vec2::new(1.1, 0.5));
+        game.set_teleporter(Vec2::new(2, 0), Some(teleporter.with_actors(true)));
+        game.move_entity(0, Vec2::new(1.0, 0.0));
+        assert_eq!(game.entities[0].position, Vec2::new(5.5, 5.5));
+
+        // walking east into the west side of a wall comes out of the south side of another
+        game.teleporters.clear();
+        game.walls
+            .extend([Vec2::new(3, 0), Vec2::new(10, 10)].map(|p| Wall::new(WallKind::Basic, p)));
+        game.add_portal(
+            (Vec2::new(3, 0), WallFace::West),
+            (Vec2::new(10, 10), WallFace::South),
+        )
+        .unwrap();
+        game.player.position = Vec2::new(2.7, 0.25);
+        game.player.direction = Vec2::new(1.0, 0.0);
+        game.move_player(Vec2::new(0.2, 0.0));
+        assert_eq!(game.player.position, Vec2::new(2.9, 0.25));
+        game.move_player(Vec2::new(0.2, 0.0));
+        let pos = game.player.position;
+        assert!((pos - Vec2::new(10.75, 11.1)).magn() < 1e-4, "{pos}");
+        assert!((game.player.direction - Vec2::new(0.0, 1.0)).magn() < 1e-4);
+
+        // the other sides of the portal walls still block
+        game.player.position = Vec2::new(3.5, 1.5);
+        game.move_player(Vec2::new(0.0, -0.4));
+        assert_eq!(game.player.position, Vec2::new(3.5, 1.5));
+        game.move_player(Vec2::new(0.0, -1.0));
+        assert_eq!(game.player.position, Vec2::new(3.5, 1.5));
+        // going diagonally into the portal tile only slides along the wall
+        game.player.position = Vec2::new(2.8, 1.2);
+        game.move_player(Vec2::new(0.3, -0.3));
+        assert!((game.player.position - Vec2::new(2.8, 0.9)).magn() < 1e-4);
+
+        // no room on the other side keeps the player on this side of the portal
+        game.walls
+            .push(Wall::new(WallKind::Basic, Vec2::new(10, 11)));
+        game.player.position = Vec2::new(2.9, 0.25);
+        game.move_player(Vec2::new(0.2, 0.0));
+        assert_eq!(game.player.position, Vec2::new(2.9, 0.25));
+        assert!(game
+            .add_portal(
+                (Vec2::new(3, 0), WallFace::Front),
+                (Vec2::new(0, 0), WallFace::West)
+            )
+            .is_err());
+    }
+}
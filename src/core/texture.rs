@@ -0,0 +1,61 @@
+use super::{Color, Game, PixelBuffer, Vec2};
+
+/// Index of a texture registered with [`Game::add_texture`].
+pub type TextureId = usize;
+
+/// Textures of the floor and ceiling of a tile, `None` uses the flat color of the
+/// [`ViewStyle`](super::ViewStyle).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Flats {
+    pub floor: Option<TextureId>,
+    pub ceiling: Option<TextureId>,
+    /// Panoramic sky replacing the ceiling, making the tile outdoor.
+    pub sky: Option<TextureId>,
+}
+
+impl PixelBuffer {
+    /// Color at texture coordinates `u`, `v` in `[0, 1)`, wrapping around.
+    pub fn sample(&self, u: f32, v: f32) -> Color {
+        let x = ((u - u.floor()) * self.width as f32) as usize;
+        let y = ((v - v.floor()) * self.height as f32) as usize;
+        self.data[y.min(self.height - 1) * self.width + x.min(self.width - 1)]
+    }
+}
+
+impl Game {
+    pub fn add_texture(&mut self, texture: PixelBuffer) -> TextureId {
+        self.textures.push(texture);
+        self.textures.len() - 1
+    }
+
+    /// Registered texture, or its current frame when animated. `None` when unknown or
+    /// empty.
+    pub fn texture(&self, id: TextureId) -> Option<&PixelBuffer> {
+        self.textures
+            .get(self.animated_texture(id))
+            .filter(|t| t.width > 0 && t.height > 0)
+    }
+
+    /// Draws the decorations showing `sprite` with a texture instead of a flat color,
+    /// `None` to go back to the color.
+    pub fn set_sprite_texture(&mut self, sprite: u16, texture: Option<TextureId>) {
+        match texture {
+            Some(texture) => self.sprite_textures.insert(sprite, texture),
+            None => self.sprite_textures.remove(&sprite),
+        };
+    }
+
+    /// Sky shown instead of the ceiling of a tile with `flats`: its own, else the one of
+    /// the map unless it has a ceiling texture.
+    pub fn sky_of(&self, flats: &Flats) -> Option<TextureId> {
+        flats.sky.or(self.sky.filter(|_| flats.ceiling.is_none()))
+    }
+
+    pub fn set_flats(&mut self, tile: Vec2<i32>, flats: Flats) {
+        if flats == Flats::default() {
+            self.flats.remove(&tile);
+        } else {
+            self.flats.insert(tile, flats);
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use super::{Color, PixelBuffer};
+
+/// Galois LFSR feedback masks giving a maximal period, indexed by bit count. The 17 bit
+/// one is the mask of the original fizzle fade.
+const LFSR_TAPS: [u32; 29] = [
+    0, 0, 0x3, 0x6, 0xc, 0x14, 0x30, 0x60, 0xb8, 0x110, 0x240, 0x500, 0x829, 0x100d, 0x2015,
+    0x6000, 0xd008, 0x12000, 0x20400, 0x40023, 0x90000, 0x140000, 0x300000, 0x420000, 0xe10000,
+    0x1200000, 0x2000023, 0x4000013, 0x9000000,
+];
+
+/// Visits every index below `count` once, in the pseudo random order of an LFSR.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Fizzle {
+    state: u32,
+    taps: u32,
+    count: u32,
+}
+
+impl Fizzle {
+    pub fn new(count: usize) -> Self {
+        // smallest LFSR whose period (2^bits - 1) covers every index
+        let bits = (u32::BITS - (count as u32).leading_zeros()).max(2) as usize;
+        Self {
+            state: 1,
+            taps: LFSR_TAPS[bits.min(LFSR_TAPS.len() - 1)],
+            count: count as u32,
+        }
+    }
+}
+
+impl Iterator for Fizzle {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.state != 0 {
+            let value = self.state;
+            let lsb = self.state & 1;
+            self.state >>= 1;
+            if lsb != 0 {
+                self.state ^= self.taps;
+            }
+            if self.state == 1 {
+                // full period done
+                self.state = 0;
+            }
+            if value - 1 < self.count {
+                return Some((value - 1) as usize);
+            }
+        }
+        None
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransitionKind {
+    /// The new frame replaces the old one pixel by pixel, in pseudo random order.
+    Fizzle,
+    /// The new frame is covered by a color pixel by pixel, like the death screen.
+    FizzleTo(Color),
+    /// The old frame blends into the new one.
+    CrossFade,
+    /// The old frame fades to a color during the first half, the new one fades in from
+    /// it during the second.
+    FadeThrough(Color),
+}
+
+/// Animation between the frame shown when it starts and the frames rendered while it
+/// runs. Advanced once per tic, then applied over each new frame.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Transition {
+    kind: TransitionKind,
+    from: PixelBuffer,
+    tics: u32,
+    elapsed: u32,
+    /// Pixels revealed so far by the fizzle kinds.
+    revealed: Vec<bool>,
+    revealed_count: usize,
+    fizzle: Fizzle,
+}
+
+impl Transition {
+    /// Starts from `from` (usually the last frame) and lasts `tics` tics.
+    pub fn new(kind: TransitionKind, from: PixelBuffer, tics: u32) -> Self {
+        let count = from.data.len();
+        let fizzle = matches!(kind, TransitionKind::Fizzle | TransitionKind::FizzleTo(_));
+        Self {
+            kind,
+            tics: tics.max(1),
+            elapsed: 0,
+            revealed: if fizzle { vec![false; count] } else { vec![] },
+            revealed_count: 0,
+            fizzle: Fizzle::new(count),
+            from,
+        }
+    }
+
+    pub fn kind(&self) -> TransitionKind {
+        self.kind
+    }
+
+    /// From 0 when started to 1 when finished.
+    pub fn progress(&self) -> f32 {
+        self.elapsed as f32 / self.tics as f32
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= self.tics
+    }
+
+    pub fn tick(&mut self) {
+        if self.is_finished() {
+            return;
+        }
+        self.elapsed += 1;
+        if self.revealed.is_empty() {
+            return;
+        }
+        let target = self.revealed.len() * self.elapsed as usize / self.tics as usize;
+        while self.revealed_count < target {
+            let Some(i) = self.fizzle.next() else {
+                break;
+            };
+            self.revealed[i] = true;
+            self.revealed_count += 1;
+        }
+    }
+
+    /// Composes the transition over `frame`, the newly rendered frame. Frames of another
+    /// size than the starting one are left untouched.
+    pub fn apply(&self, frame: &mut PixelBuffer) {
+        if frame.width != self.from.width || frame.height != self.from.height {
+            return;
+        }
+        let t = self.progress();
+        match self.kind {
+            TransitionKind::Fizzle => {
+                for (i, c) in frame.data.iter_mut().enumerate() {
+                    if !self.revealed[i] {
+                        *c = self.from.data[i];
+                    }
+                }
+            }
+            TransitionKind::FizzleTo(color) => {
+                for (i, c) in frame.data.iter_mut().enumerate() {
+                    if self.revealed[i] {
+                        *c = color;
+                    }
+                }
+            }
+            TransitionKind::CrossFade => {
+                for (c, from) in frame.data.iter_mut().zip(&self.from.data) {
+                    *c = from.lerp(*c, t);
+                }
+            }
+            TransitionKind::FadeThrough(color) => {
+                if t < 0.5 {
+                    for (c, from) in frame.data.iter_mut().zip(&self.from.data) {
+                        *c = from.lerp(color, t * 2.0);
+                    }
+                } else {
+                    for c in frame.data.iter_mut() {
+                        *c = color.lerp(*c, t * 2.0 - 1.0);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Fizzle, Transition, TransitionKind};
+    use crate::{Color, PixelBuffer};
+
+    #[test]
+    fn fizzle_visits_every_index_once() {
+        for count in [1, 2, 3, 64, 1000, 320 * 200] {
+            let mut seen = vec![false; count];
+            for i in Fizzle::new(count) {
+                assert!(!seen[i], "{} visited twice for {}", i, count);
+                seen[i] = true;
+            }
+            assert!(seen.iter().all(|v| *v), "missing indices for {}", count);
+        }
+    }
+
+    #[test]
+    fn fizzle_and_fade() {
+        let mut old = PixelBuffer::new(8, 8);
+        old.fill(Color::white());
+        let new = PixelBuffer::new(8, 8);
+        let mut transition = Transition::new(TransitionKind::Fizzle, old.clone(), 4);
+        let shown = |transition: &Transition| {
+            let mut frame = new.clone();
+            transition.apply(&mut frame);
+            frame.data.iter().filter(|c| **c == Color::black()).count()
+        };
+        assert_eq!(shown(&transition), 0);
+        transition.tick();
+        assert_eq!(shown(&transition), 16);
+        (0..3).for_each(|_| transition.tick());
+        assert!(transition.is_finished());
+        assert_eq!(shown(&transition), 64);
+
+        let mut fade = Transition::new(TransitionKind::FadeThrough(Color::red()), old, 4);
+        fade.tick();
+        let mut frame = new.clone();
+        fade.apply(&mut frame);
+        assert_eq!(frame.data[0], Color::new(255, 128, 128, 255));
+        (0..3).for_each(|_| fade.tick());
+        let mut frame = new.clone();
+        fade.apply(&mut frame);
+        assert_eq!(frame, new);
+    }
+}
@@ -0,0 +1,103 @@
+use std::sync::{Arc, Mutex};
+
+use lazy_static::lazy_static;
+use wasm_bindgen::prelude::{wasm_bindgen, JsError};
+
+use crate::{Error, Imf, ImfPlayer, Mixer, MusicLibrary, Sample, SampleId, GAME};
+
+lazy_static! {
+    pub static ref MIXER: Arc<Mutex<Option<Mixer>>> = Arc::new(Mutex::new(None));
+    pub static ref MUSIC: Arc<Mutex<MusicLibrary>> = Arc::new(Mutex::new(MusicLibrary::new()));
+}
+
+fn with_mixer<T>(f: impl FnOnce(&mut Mixer) -> T) -> Result<T, JsError> {
+    let mut mixer = MIXER.lock().unwrap();
+    match mixer.as_mut() {
+        Some(mixer) => Ok(f(mixer)),
+        None => Err(JsError::new("audio not initialized, call init_audio first")),
+    }
+}
+
+/// Creates the mixer, `rate` should match the `AudioContext` sample rate.
+#[wasm_bindgen]
+pub fn init_audio(rate: u32, voices: usize) -> Result<(), JsError> {
+    // half a second of buffered audio
+    *MIXER.lock().unwrap() = Some(Mixer::new(rate, voices, rate as usize / 2)?);
+    Ok(())
+}
+
+/// Registers an unsigned 8 bit mono sound, returns its id for `play_sound`.
+#[wasm_bindgen]
+pub fn load_sound(rate: u32, pcm: &[u8]) -> Result<SampleId, JsError> {
+    if rate == 0 {
+        return Err(Error::InvalidAudio("sound rate is 0".to_string()).into());
+    }
+    with_mixer(|m| m.add_sample(Sample::from_u8(rate, pcm)))
+}
+
+/// Plays a sound, returns the voice used or -1 when it was dropped.
+#[wasm_bindgen]
+pub fn play_sound(id: SampleId, volume: f32, pan: f32, priority: u8) -> Result<i32, JsError> {
+    let voice = with_mixer(|m| m.play(id, volume, pan, priority))??;
+    Ok(voice.map_or(-1, |v| v as i32))
+}
+
+#[wasm_bindgen]
+pub fn set_audio_volume(volume: f32) -> Result<(), JsError> {
+    with_mixer(|m| m.set_volume(volume))
+}
+
+/// Fills `out` with interleaved stereo frames, meant to be called from the
+/// `AudioWorklet` feeding code. Returns the number of values written.
+#[wasm_bindgen]
+pub fn pull_audio(out: &mut [f32]) -> Result<usize, JsError> {
+    with_mixer(|m| m.read(out))
+}
+
+/// Registers an IMF song (type 0 or type 1), returns its id.
+#[wasm_bindgen]
+pub fn load_music(data: &[u8]) -> Result<usize, JsError> {
+    let song = Imf::from_bytes(data)?;
+    Ok(MUSIC.lock().unwrap().add_song(song))
+}
+
+#[wasm_bindgen]
+pub fn set_level_music(level: u16, id: usize) {
+    MUSIC.lock().unwrap().set_level_song(level, id);
+}
+
+fn start_music(song: Option<Imf>) -> Result<(), JsError> {
+    let song = song.ok_or_else(|| JsError::new("unknown song"))?;
+    with_mixer(|m| {
+        let player = ImfPlayer::new(song, m.rate())?;
+        m.set_music(Some(Box::new(player)));
+        Ok(())
+    })?
+}
+
+#[wasm_bindgen]
+pub fn play_music(id: usize) -> Result<(), JsError> {
+    let song = MUSIC.lock().unwrap().song(id).cloned();
+    start_music(song)
+}
+
+/// Plays the song assigned to the running game's level.
+#[wasm_bindgen]
+pub fn play_level_music() -> Result<(), JsError> {
+    let level = match GAME.lock().unwrap().as_ref() {
+        Some(game) => game.progress.level,
+        None => return Err(JsError::new("no game running")),
+    };
+    let song = MUSIC.lock().unwrap().level_song(level).cloned();
+    start_music(song)
+}
+
+#[wasm_bindgen]
+pub fn stop_music() -> Result<(), JsError> {
+    with_mixer(|m| m.set_music(None))
+}
+
+#[wasm_bindgen]
+pub fn set_music_volume(volume: f32) -> Result<(), JsError> {
+    with_mixer(|m| m.set_music_volume(volume))
+}
@@ -1,10 +1,18 @@
+#[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::wasm_bindgen;
 
+#[cfg(target_arch = "wasm32")]
 #[wasm_bindgen(js_namespace = console)]
 extern "C" {
     pub fn log(s: String);
 }
 
+/// Native builds (tests, tools) have no JS console, print to stdout instead.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn log(s: String) {
+    println!("{}", s);
+}
+
 #[macro_export]
 macro_rules! console_log {
     ($fmt:expr) => {
@@ -0,0 +1,74 @@
+use std::sync::Mutex;
+
+use lazy_static::lazy_static;
+use wasm_bindgen::prelude::{wasm_bindgen, JsError};
+
+use crate::{console_log, Demo, DemoPlayer, DemoRecorder, Game, TicCmd, GAME};
+
+enum DemoState {
+    Idle,
+    Recording(DemoRecorder),
+    Playing(DemoPlayer),
+}
+
+lazy_static! {
+    static ref DEMO: Mutex<DemoState> = Mutex::new(DemoState::Idle);
+}
+
+/// Runs a tic of the live game, recording it or overriding the input with the
+/// demo being played back. Once the demo is over the tic runs from `cmd`, failing with
+/// [`Error::DemoDesync`](crate::Error::DemoDesync) when the game did not end up in the
+/// recorded state.
+pub fn run_tic(game: &mut Game, cmd: TicCmd) -> crate::Result<()> {
+    let mut demo = DEMO.lock().unwrap();
+    match &mut *demo {
+        DemoState::Idle => game.tick(&cmd),
+        DemoState::Recording(rec) => rec.tick(game, cmd),
+        DemoState::Playing(player) => {
+            if !player.tick(game) {
+                let result = player.finish(game);
+                console_log!("demo playback finished");
+                *demo = DemoState::Idle;
+                game.tick(&cmd);
+                return result;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Starts recording the input of the running game. The demo only replays on this starting
+/// state, keep a `save_game` taken now along with it.
+#[wasm_bindgen]
+pub fn start_demo_recording() -> Result<(), JsError> {
+    let g = GAME.lock().unwrap();
+    let game = g.as_ref().ok_or_else(|| JsError::new("no game running"))?;
+    *DEMO.lock().unwrap() = DemoState::Recording(DemoRecorder::start(game));
+    Ok(())
+}
+
+/// Stops the recording and returns the demo file.
+#[wasm_bindgen]
+pub fn stop_demo_recording() -> Result<Vec<u8>, JsError> {
+    let g = GAME.lock().unwrap();
+    let game = g.as_ref().ok_or_else(|| JsError::new("no game running"))?;
+    let mut demo = DEMO.lock().unwrap();
+    match std::mem::replace(&mut *demo, DemoState::Idle) {
+        DemoState::Recording(rec) => Ok(rec.finish(game).to_bytes()),
+        state => {
+            *demo = state;
+            Err(JsError::new("not recording a demo"))
+        }
+    }
+}
+
+/// Plays a demo back, the running game must be in the state the recording started from
+/// (e.g. restored with `load_game`).
+#[wasm_bindgen]
+pub fn play_demo(data: &[u8]) -> Result<(), JsError> {
+    let demo = Demo::from_bytes(data)?;
+    let g = GAME.lock().unwrap();
+    let game = g.as_ref().ok_or_else(|| JsError::new("no game running"))?;
+    *DEMO.lock().unwrap() = DemoState::Playing(DemoPlayer::new(demo, game)?);
+    Ok(())
+}
@@ -1,10 +1,54 @@
-use wasm_bindgen::prelude::wasm_bindgen;
+use std::sync::Mutex;
 
-use crate::{console_log, Color, Vec2, Wall, WallKind, BUFFER};
-use crate::{Game, GAME};
+use lazy_static::lazy_static;
+use wasm_bindgen::prelude::{wasm_bindgen, JsError};
 
+use crate::{
+    console_log, reset_buffer, run_tic, Difficulty, Teleporter, TicClock, TicCmd, Vec2, Wall,
+    WallFace, WallKind, BUFFER,
+};
+use crate::{
+    Animation, AnimationMode, Color, ColorMap, Emitter, Flats, Fog, FogFalloff, Game,
+    IndexedBuffer, Palette, ParticleStyle, PixelBuffer, RenderScale, ScaleMode, Transition,
+    TransitionKind, GAME,
+};
+
+lazy_static! {
+    /// Input gathered from JS events until the next tic consumes it.
+    static ref INPUT: Mutex<TicCmd> = Mutex::new(TicCmd::default());
+    static ref CLOCK: Mutex<TicClock> = Mutex::new(TicClock::new());
+    /// Frame at the internal render resolution, before upscaling into `BUFFER`.
+    static ref SCENE: Mutex<PixelBuffer> = Mutex::new(PixelBuffer::new(0, 0));
+    /// 8 bit frame of the 256 color mode.
+    static ref INDEXED: Mutex<IndexedBuffer> = Mutex::new(IndexedBuffer::new(0, 0));
+    /// Colors given to `load_palette`, used by the 256 color mode.
+    static ref PALETTE: Mutex<Option<ColorMap>> = Mutex::new(None);
+    /// Screen transition composed over the frames until it finishes.
+    static ref TRANSITION: Mutex<Option<Transition>> = Mutex::new(None);
+}
+
+/// Color from a 0xRRGGBB value.
+fn rgb(color: u32) -> Color {
+    Color::new((color >> 16) as u8, (color >> 8) as u8, color as u8, 255)
+}
+
+/// Length of the fizzle to red when the player dies, in tics.
+const DEATH_FIZZLE_TICS: u32 = 70;
+
+/// Starts a transition from the current frame, lasting `tics` tics.
+fn transition(kind: TransitionKind, tics: u32) {
+    let from = BUFFER.lock().unwrap().clone();
+    *TRANSITION.lock().unwrap() = Some(Transition::new(kind, from, tics));
+}
+
+/// `difficulty` ranges from 0 ("Can I play, Daddy?") to 3 ("I am Death incarnate!"),
+/// `seed` initializes the game's random generator (e.g. from `Math.random()`).
 #[wasm_bindgen]
-pub fn init_game(w: usize, h: usize) {
+pub fn init_game(w: usize, h: usize, difficulty: u8, seed: u32) {
+    let difficulty = Difficulty::from_index(difficulty).unwrap_or_else(|| {
+        console_log!("invalid difficulty {}, using default", difficulty);
+        Difficulty::default()
+    });
     let mut g = GAME.lock().unwrap();
     let mut walls = vec![];
     for y in -2..=2 {
@@ -14,40 +58,588 @@ pub fn init_game(w: usize, h: usize) {
             }
         }
     }
-    *g = Some(Game::new().with_walls(&walls));
+    *g = Some(
+        Game::new()
+            .with_difficulty(difficulty)
+            .with_seed(seed as u64)
+            .with_walls(&walls),
+    );
     console_log!("Walls: {:#?}", g.as_ref().unwrap().ascii_walls());
+    g.as_mut().unwrap().resize(w, h);
+    drop(g);
+    reset_buffer(w, h);
+    transition(TransitionKind::Fizzle, 35);
+}
+
+/// Reallocates the framebuffer for a `w`x`h` canvas and updates the camera projection.
+/// Call `get_buffer` again afterwards, or compare `buffer_generation`.
+#[wasm_bindgen]
+pub fn resize(w: usize, h: usize) {
+    if let Some(game) = GAME.lock().unwrap().as_mut() {
+        game.resize(w, h);
+    }
+    reset_buffer(w, h);
+}
+
+/// Runs the tics due since the last frame, then renders the game into the framebuffer.
+fn step(game: &mut Game, dt: f32) {
+    let tics = CLOCK.lock().unwrap().advance(dt);
+    for _ in 0..tics {
+        let mut input = INPUT.lock().unwrap();
+        let cmd = *input;
+        input.turn = 0;
+        drop(input);
+        let alive = game.player.health > 0;
+        if let Err(e) = run_tic(game, cmd) {
+            console_log!("{}", e);
+        }
+        if alive && game.player.health <= 0 {
+            transition(TransitionKind::FizzleTo(Color::red()), DEATH_FIZZLE_TICS);
+        }
+        if let Some(transition) = TRANSITION.lock().unwrap().as_mut() {
+            transition.tick();
+        }
+    }
     let mut buf = BUFFER.lock().unwrap();
-    buf.data.clear();
-    buf.data.extend(vec![
-        Color {
-            r: 0,
-            g: 0,
-            b: 0,
-            a: 255
-        };
-        w * h * 4
-    ]);
-    buf.width = w;
-    buf.height = h;
+    game.render_scaled(
+        dt,
+        &mut SCENE.lock().unwrap(),
+        &mut INDEXED.lock().unwrap(),
+        &mut buf,
+    );
+    let mut transition = TRANSITION.lock().unwrap();
+    if let Some(t) = transition.as_ref() {
+        t.apply(&mut buf);
+        // a fizzle to a color stays on screen, like the death screen
+        if t.is_finished() && !matches!(t.kind(), TransitionKind::FizzleTo(_)) {
+            *transition = None;
+        }
+    }
 }
 
+/// Runs the tics due since the last frame, then renders the game. `dt` is in milliseconds.
+/// The frame is read from the memory described by `get_buffer`.
 #[wasm_bindgen]
 pub fn render_game(dt: f32) {
     let mut game = GAME.lock().unwrap();
-    let game = game.as_mut().unwrap();
-    let mut buf = BUFFER.lock().unwrap();
-    game.render(dt, &mut buf);
-    let (ray, intersections) = game.shoot_ray(game.player.position, game.player.direction);
-    // for (pos, wall) in intersections {
-    //     console_log!("ray intersected with {:?} wall at {}", wall.kind, pos);
-    // }
-    // game.player.direction.rotate(1.0f32);
+    step(game.as_mut().unwrap(), dt);
+}
+
+/// Like `render_game`, but copies the frame as RGBA8 into `out` (e.g. an `ImageData`'s
+/// data), which must hold exactly `width * height * 4` bytes.
+#[wasm_bindgen]
+pub fn render_into(dt: f32, out: &mut [u8]) -> Result<(), JsError> {
+    let mut game = GAME.lock().unwrap();
+    let game = game
+        .as_mut()
+        .ok_or_else(|| JsError::new("no game running"))?;
+    step(game, dt);
+    BUFFER.lock().unwrap().write_rgba(out)?;
+    Ok(())
 }
 
 #[wasm_bindgen]
 pub fn move_mouse(x: i32, _: i32) {
+    let mut input = INPUT.lock().unwrap();
+    let steps = (x as f32 / TicCmd::TURN_UNIT) as i16;
+    input.turn = input.turn.saturating_add(steps);
+}
+
+/// Movement held by the player, `forward` and `strafe` range from -127 to 127
+/// and `buttons` is a combination of the `TicCmd` button flags.
+#[wasm_bindgen]
+pub fn set_input(forward: i8, strafe: i8, buttons: u8) {
+    let mut input = INPUT.lock().unwrap();
+    input.forward = forward;
+    input.strafe = strafe;
+    input.buttons = buttons;
+}
+
+/// Snapshot of the running game, to be stored by JS (e.g. in IndexedDB).
+#[wasm_bindgen]
+pub fn save_game() -> Result<Vec<u8>, JsError> {
+    let g = GAME.lock().unwrap();
+    match g.as_ref() {
+        Some(game) => Ok(game.save()),
+        None => Err(JsError::new("no game running")),
+    }
+}
+
+/// Replaces the running game with a snapshot returned by `save_game`, loaded textures
+/// and view settings are kept.
+#[wasm_bindgen]
+pub fn load_game(data: &[u8]) -> Result<(), JsError> {
+    let (w, h) = {
+        let buf = BUFFER.lock().unwrap();
+        (buf.width, buf.height)
+    };
+    {
+        let mut g = GAME.lock().unwrap();
+        match g.as_mut() {
+            Some(game) => game.restore(data)?,
+            None => {
+                let mut game = Game::load(data)?;
+                game.resize(w, h);
+                *g = Some(game);
+            }
+        }
+    }
+    transition(TransitionKind::Fizzle, 35);
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub fn toggle_automap() {
+    if let Some(game) = GAME.lock().unwrap().as_mut() {
+        game.automap.toggle();
+    }
+}
+
+/// Pans the automap by a number of screen pixels.
+#[wasm_bindgen]
+pub fn pan_automap(dx: f32, dy: f32) {
+    if let Some(game) = GAME.lock().unwrap().as_mut() {
+        let from = game.automap_center();
+        game.automap.pan(from, Vec2::new(dx, dy));
+    }
+}
+
+#[wasm_bindgen]
+pub fn zoom_automap(factor: f32) {
+    if let Some(game) = GAME.lock().unwrap().as_mut() {
+        game.automap.zoom_by(factor);
+    }
+}
+
+/// Centers the automap back on the player.
+#[wasm_bindgen]
+pub fn recenter_automap() {
+    if let Some(game) = GAME.lock().unwrap().as_mut() {
+        game.automap.recenter();
+    }
+}
+
+/// Renders the world at `w`x`h` and upscales it to the canvas, `mode` being 0 (stretch),
+/// 1 (letterbox) or 2 (integer multiples only). A zero size renders at the canvas size.
+#[wasm_bindgen]
+pub fn set_render_scale(w: usize, h: usize, mode: u8) {
+    let mode = match mode {
+        0 => ScaleMode::Stretch,
+        2 => ScaleMode::Integer,
+        _ => ScaleMode::Letterbox,
+    };
+    if let Some(game) = GAME.lock().unwrap().as_mut() {
+        game.set_render_scale(RenderScale {
+            resolution: (w > 0 && h > 0).then_some(Vec2::new(w, h)),
+            mode,
+            ..game.render_scale
+        });
+    }
+}
+
+/// Starts a screen transition from the current frame to the next ones. `kind` is
+/// 0 (fizzle), 1 (fizzle to `color`), 2 (cross-fade) or 3 (fade through `color`),
+/// `color` being 0xRRGGBB. `tics` is the duration at 70 tics per second.
+#[wasm_bindgen]
+pub fn start_transition(kind: u8, tics: u32, color: u32) {
+    let color = rgb(color);
+    let kind = match kind {
+        0 => TransitionKind::Fizzle,
+        1 => TransitionKind::FizzleTo(color),
+        2 => TransitionKind::CrossFade,
+        _ => TransitionKind::FadeThrough(color),
+    };
+    transition(kind, tics);
+}
+
+/// Removes the current transition, such as a finished death fizzle.
+#[wasm_bindgen]
+pub fn clear_transition() {
+    *TRANSITION.lock().unwrap() = None;
+}
+
+/// Distance fog of the 3D view: `falloff` is 0 (none, the original flat lighting),
+/// 1 (linear between `start` and `end`), 2 (exponential) or 3 (exponential squared),
+/// `color` being 0xRRGGBB.
+#[wasm_bindgen]
+pub fn set_fog(falloff: u8, color: u32, start: f32, end: f32, density: f32) {
+    let falloff = match falloff {
+        0 => None,
+        1 => Some(FogFalloff::Linear),
+        2 => Some(FogFalloff::Exponential),
+        _ => Some(FogFalloff::ExponentialSquared),
+    };
+    let color = rgb(color);
+    if let Some(game) = GAME.lock().unwrap().as_mut() {
+        game.fog = falloff.map(|falloff| Fog {
+            start,
+            end,
+            density,
+            ..Fog::new(color, falloff)
+        });
+    }
+}
+
+/// Registers a texture of `width` x `height` RGBA pixels, returns its id.
+#[wasm_bindgen]
+pub fn load_texture(width: usize, height: usize, rgba: &[u8]) -> Result<usize, JsError> {
+    let texture = PixelBuffer::from_rgba(width, height, rgba)?;
+    let mut g = GAME.lock().unwrap();
+    let game = g
+        .as_mut()
+        .ok_or_else(|| JsError::new("game not initialized"))?;
+    Ok(game.add_texture(texture))
+}
+
+/// Sets the palette of the 256 color mode from the 768 bytes of a VGA palette, like the
+/// original `GAMEPAL`.
+#[wasm_bindgen]
+pub fn load_palette(data: &[u8]) -> Result<(), JsError> {
+    let map = ColorMap::new(Palette::from_vga(data)?);
+    if let Some(game) = GAME.lock().unwrap().as_mut() {
+        if game.indexed.is_some() {
+            game.indexed = Some(map.clone());
+        }
+    }
+    *PALETTE.lock().unwrap() = Some(map);
+    Ok(())
+}
+
+/// Renders in the 256 colors of the palette given to `load_palette`, the screen flashes
+/// and fades then shift the palette instead of every pixel.
+#[wasm_bindgen]
+pub fn set_indexed_mode(enabled: bool) -> Result<(), JsError> {
+    let map = match enabled {
+        true => Some(
+            PALETTE
+                .lock()
+                .unwrap()
+                .clone()
+                .ok_or_else(|| JsError::new("no palette loaded, call load_palette first"))?,
+        ),
+        false => None,
+    };
+    let mut g = GAME.lock().unwrap();
+    let game = g
+        .as_mut()
+        .ok_or_else(|| JsError::new("game not initialized"))?;
+    game.indexed = map;
+    Ok(())
+}
+
+/// Registers a texture of `width` x `height` palette indices, converted through the 768
+/// bytes of a VGA `palette` like the original `GAMEPAL`, returns its id.
+#[wasm_bindgen]
+pub fn load_indexed_texture(
+    width: usize,
+    height: usize,
+    indices: &[u8],
+    palette: &[u8],
+) -> Result<usize, JsError> {
+    let texture = Palette::from_vga(palette)?.texture(width, height, indices)?;
     let mut g = GAME.lock().unwrap();
-    let g = g.as_mut().unwrap();
-    g.player.direction.rotate(x as f32);
-    g.player.direction.normalize();
+    let game = g
+        .as_mut()
+        .ok_or_else(|| JsError::new("game not initialized"))?;
+    Ok(game.add_texture(texture))
+}
+
+/// Sets the floor texture of a tile, `undefined` for the flat color.
+#[wasm_bindgen]
+pub fn set_floor_texture(x: i32, y: i32, texture: Option<usize>) {
+    if let Some(game) = GAME.lock().unwrap().as_mut() {
+        let tile = Vec2::new(x, y);
+        let flats = game.flats.get(&tile).copied().unwrap_or_default();
+        game.set_flats(
+            tile,
+            Flats {
+                floor: texture,
+                ..flats
+            },
+        );
+    }
+}
+
+/// Sets the ceiling texture of a tile, `undefined` for the flat color.
+#[wasm_bindgen]
+pub fn set_ceiling_texture(x: i32, y: i32, texture: Option<usize>) {
+    if let Some(game) = GAME.lock().unwrap().as_mut() {
+        let tile = Vec2::new(x, y);
+        let flats = game.flats.get(&tile).copied().unwrap_or_default();
+        game.set_flats(
+            tile,
+            Flats {
+                ceiling: texture,
+                ..flats
+            },
+        );
+    }
+}
+
+/// Sets the height of the wall on a tile, 1 being the height of the ceiling.
+#[wasm_bindgen]
+pub fn set_wall_height(x: i32, y: i32, height: f32) {
+    if let Some(game) = GAME.lock().unwrap().as_mut() {
+        let tile = Vec2::new(x, y);
+        if let Some(wall) = game.walls.iter_mut().find(|w| w.position == tile) {
+            wall.height = height;
+        }
+    }
+}
+
+/// Raises or lowers the floor of a tile, walls standing on it included.
+#[wasm_bindgen]
+pub fn set_floor_height(x: i32, y: i32, height: f32) {
+    if let Some(game) = GAME.lock().unwrap().as_mut() {
+        game.set_floor_height(Vec2::new(x, y), height);
+    }
+}
+
+/// Adds a wall of no thickness across a tile, along the y axis when `vertical`, `offset`
+/// tiles from its west (or north) edge. Replaces the wall already on the tile.
+#[wasm_bindgen]
+pub fn add_thin_wall(x: i32, y: i32, vertical: bool, offset: f32) -> Result<(), JsError> {
+    check_offset(offset)?;
+    place_wall(Wall::new(
+        WallKind::Thin { vertical, offset },
+        Vec2::new(x, y),
+    ))
+}
+
+/// Adds a wall across a tile from corner to corner, north-west to south-east unless
+/// `flipped`. Replaces the wall already on the tile.
+#[wasm_bindgen]
+pub fn add_diagonal_wall(x: i32, y: i32, flipped: bool) -> Result<(), JsError> {
+    place_wall(Wall::new(WallKind::Diagonal { flipped }, Vec2::new(x, y)))
+}
+
+fn check_offset(offset: f32) -> Result<(), JsError> {
+    if !(0f32..=1f32).contains(&offset) {
+        return Err(JsError::new(&format!(
+            "wall offset {} is not within 0..=1",
+            offset
+        )));
+    }
+    Ok(())
+}
+
+/// Puts `wall` on its tile in place of the one already there.
+fn place_wall(wall: Wall) -> Result<(), JsError> {
+    let mut g = GAME.lock().unwrap();
+    let game = g
+        .as_mut()
+        .ok_or_else(|| JsError::new("game not initialized"))?;
+    game.walls.retain(|w| w.position != wall.position);
+    game.walls.push(wall);
+    Ok(())
+}
+
+/// Adds bars, a fence or a window across a tile, see [`add_thin_wall`]. Transparent
+/// parts of its texture show what is behind. Replaces the wall already on the tile.
+#[wasm_bindgen]
+pub fn add_see_through_wall(x: i32, y: i32, vertical: bool, offset: f32) -> Result<(), JsError> {
+    check_offset(offset)?;
+    place_wall(Wall::new(
+        WallKind::SeeThrough { vertical, offset },
+        Vec2::new(x, y),
+    ))
+}
+
+/// Sets whether the wall on a tile stops movement and the exploration of what is behind.
+#[wasm_bindgen]
+pub fn set_wall_blocking(x: i32, y: i32, movement: bool, sight: bool) {
+    if let Some(game) = GAME.lock().unwrap().as_mut() {
+        let tile = Vec2::new(x, y);
+        if let Some(wall) = game.walls.iter_mut().find(|w| w.position == tile) {
+            wall.blocks_movement = movement;
+            wall.blocks_sight = sight;
+        }
+    }
+}
+
+/// Sets the texture of the wall on a tile, `undefined` for the flat color.
+#[wasm_bindgen]
+pub fn set_wall_texture(x: i32, y: i32, texture: Option<usize>) {
+    if let Some(game) = GAME.lock().unwrap().as_mut() {
+        let tile = Vec2::new(x, y);
+        if let Some(wall) = game.walls.iter_mut().find(|w| w.position == tile) {
+            wall.texture = texture;
+        }
+    }
+}
+
+/// Sets the sky of the whole map, shown over every tile without a ceiling texture,
+/// `undefined` for none.
+#[wasm_bindgen]
+pub fn set_sky(texture: Option<usize>) {
+    if let Some(game) = GAME.lock().unwrap().as_mut() {
+        game.sky = texture;
+    }
+}
+
+/// Makes a tile outdoor under a sky texture, `undefined` to make it indoor again.
+#[wasm_bindgen]
+pub fn set_tile_sky(x: i32, y: i32, texture: Option<usize>) {
+    if let Some(game) = GAME.lock().unwrap().as_mut() {
+        let tile = Vec2::new(x, y);
+        let flats = game.flats.get(&tile).copied().unwrap_or_default();
+        game.set_flats(
+            tile,
+            Flats {
+                sky: texture,
+                ..flats
+            },
+        );
+    }
+}
+
+/// Plays the textures `frames` in place of `texture`, each for `frame_tics` tics, back
+/// and forth when `ping_pong`. An empty frame list stops the animation.
+#[wasm_bindgen]
+pub fn set_animation(texture: usize, frames: &[u32], frame_tics: u32, ping_pong: bool) {
+    let animation = (!frames.is_empty()).then(|| {
+        let frames = frames.iter().map(|f| *f as usize).collect();
+        let mode = match ping_pong {
+            true => AnimationMode::PingPong,
+            false => AnimationMode::Loop,
+        };
+        Animation::new(frames, frame_tics).with_mode(mode)
+    });
+    if let Some(game) = GAME.lock().unwrap().as_mut() {
+        game.set_animation(texture, animation);
+    }
+}
+
+/// Draws the decorations showing `sprite` with a texture, `undefined` for the flat color.
+#[wasm_bindgen]
+pub fn set_sprite_texture(sprite: u16, texture: Option<usize>) {
+    if let Some(game) = GAME.lock().unwrap().as_mut() {
+        game.set_sprite_texture(sprite, texture);
+    }
+}
+
+/// Draws the bullet holes with a texture, `undefined` for the flat color.
+#[wasm_bindgen]
+pub fn set_decal_texture(texture: Option<usize>) {
+    if let Some(game) = GAME.lock().unwrap().as_mut() {
+        game.decal_texture = texture;
+    }
+}
+
+/// Removes every bullet hole from the walls.
+#[wasm_bindgen]
+pub fn clear_decals() {
+    if let Some(game) = GAME.lock().unwrap().as_mut() {
+        game.decals.clear();
+    }
+}
+
+/// Emits particles at `x`, `y`, `height` tiles above the floor level 0: `style` is
+/// 0 (blood), 1 (sparks) or 2 (smoke). `burst` particles every `interval` tics, `bursts`
+/// times or until `clear_particles` when `undefined`.
+#[wasm_bindgen]
+pub fn add_emitter(
+    x: f32,
+    y: f32,
+    height: f32,
+    style: u8,
+    burst: u16,
+    interval: u16,
+    bursts: Option<u16>,
+) {
+    let style = match style {
+        0 => ParticleStyle::BLOOD,
+        1 => ParticleStyle::SPARKS,
+        _ => ParticleStyle::SMOKE,
+    };
+    if let Some(game) = GAME.lock().unwrap().as_mut() {
+        let emitter = Emitter::new(Vec2::new(x, y), height, style)
+            .with_burst(burst)
+            .with_interval(interval)
+            .with_bursts(bursts);
+        game.particles.add_emitter(emitter);
+    }
+}
+
+/// Removes every particle and emitter.
+#[wasm_bindgen]
+pub fn clear_particles() {
+    if let Some(game) = GAME.lock().unwrap().as_mut() {
+        game.particles.clear();
+    }
+}
+
+/// Makes a tile send whoever walks into it to `target_x`, `target_y`, facing `dir_x`,
+/// `dir_y` or keeping their facing when both are 0. `actors` also sends the enemies.
+#[wasm_bindgen]
+pub fn set_teleporter(
+    x: i32,
+    y: i32,
+    target_x: i32,
+    target_y: i32,
+    dir_x: f32,
+    dir_y: f32,
+    actors: bool,
+) {
+    let dir = Vec2::new(dir_x, dir_y);
+    let teleporter = Teleporter::new(Vec2::new(target_x, target_y))
+        .with_direction((dir != Vec2::zero()).then_some(dir))
+        .with_actors(actors);
+    if let Some(game) = GAME.lock().unwrap().as_mut() {
+        game.set_teleporter(Vec2::new(x, y), Some(teleporter));
+    }
+}
+
+/// Removes the teleporter of a tile.
+#[wasm_bindgen]
+pub fn clear_teleporter(x: i32, y: i32) {
+    if let Some(game) = GAME.lock().unwrap().as_mut() {
+        game.set_teleporter(Vec2::new(x, y), None);
+    }
+}
+
+/// Side of a tile from 0 (north), 1 (south), 2 (east) or 3 (west).
+fn wall_face(face: u8) -> Result<WallFace, JsError> {
+    match face {
+        0 => Ok(WallFace::North),
+        1 => Ok(WallFace::South),
+        2 => Ok(WallFace::East),
+        3 => Ok(WallFace::West),
+        _ => Err(JsError::new(&format!(
+            "wall face {} is not within 0..=3",
+            face
+        ))),
+    }
+}
+
+/// Links two wall sides with a portal, `face` being 0 (north), 1 (south), 2 (east) or
+/// 3 (west): what goes into one comes out of the other.
+#[wasm_bindgen]
+pub fn add_portal(
+    x: i32,
+    y: i32,
+    face: u8,
+    to_x: i32,
+    to_y: i32,
+    to_face: u8,
+) -> Result<(), JsError> {
+    let mut g = GAME.lock().unwrap();
+    let game = g
+        .as_mut()
+        .ok_or_else(|| JsError::new("game not initialized"))?;
+    game.add_portal(
+        (Vec2::new(x, y), wall_face(face)?),
+        (Vec2::new(to_x, to_y), wall_face(to_face)?),
+    )?;
+    Ok(())
+}
+
+/// Removes the portal of a wall side and the one it is linked to.
+#[wasm_bindgen]
+pub fn remove_portal(x: i32, y: i32, face: u8) -> Result<(), JsError> {
+    let face = wall_face(face)?;
+    if let Some(game) = GAME.lock().unwrap().as_mut() {
+        game.remove_portal((Vec2::new(x, y), face));
+    }
+    Ok(())
 }
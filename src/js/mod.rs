@@ -1,8 +1,12 @@
-pub mod pixel_buf;
+pub mod audio;
+pub mod demo;
 pub mod game;
+pub mod pixel_buf;
 #[macro_use]
 pub mod console;
 
-pub use pixel_buf::*;
+pub use audio::*;
 pub use console::*;
-pub use game::*;
\ No newline at end of file
+pub use demo::*;
+pub use game::*;
+pub use pixel_buf::*;
@@ -1,24 +1,33 @@
-use std::sync::{Arc, Mutex};
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc, Mutex,
+};
 
 use wasm_bindgen::prelude::wasm_bindgen;
 
+/// Location of the framebuffer in wasm memory: `len` RGBA8 bytes at `addr`.
+///
+/// The location changes whenever the framebuffer is reallocated, which bumps
+/// `generation`. Views over `memory.buffer` must also be recreated when the memory grows.
 #[wasm_bindgen]
 pub struct JSPixelBuffer {
     addr: usize,
     len: usize,
     width: usize,
     height: usize,
+    generation: u32,
 }
 
 #[wasm_bindgen]
 impl JSPixelBuffer {
     #[wasm_bindgen(constructor)]
-    pub fn new(addr: usize, len: usize, w: usize, h: usize) -> Self {
+    pub fn new(addr: usize, len: usize, w: usize, h: usize, generation: u32) -> Self {
         Self {
             addr,
             len,
             width: w,
             height: h,
+            generation,
         }
     }
 
@@ -32,6 +41,12 @@ impl JSPixelBuffer {
         self.len
     }
 
+    /// Pairs `len` as Rust expects of any type with a length.
+    #[wasm_bindgen(getter)]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
     #[wasm_bindgen(getter)]
     pub fn width(&self) -> usize {
         self.width
@@ -41,24 +56,45 @@ impl JSPixelBuffer {
     pub fn height(&self) -> usize {
         self.height
     }
-}
 
+    #[wasm_bindgen(getter)]
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+}
 
 use lazy_static::lazy_static;
 
-use crate::{PixelBuffer};
+use crate::PixelBuffer;
 
 lazy_static! {
-    pub static ref BUFFER: Arc<Mutex<PixelBuffer>> = Arc::new(Mutex::new(PixelBuffer {
-        data: vec![],
-        width: 0,
-        height: 0
-    }));
+    pub static ref BUFFER: Arc<Mutex<PixelBuffer>> = Arc::new(Mutex::new(PixelBuffer::new(0, 0)));
+}
+
+static GENERATION: AtomicU32 = AtomicU32::new(0);
+
+/// Replaces the framebuffer with a black `w`x`h` one.
+pub fn reset_buffer(w: usize, h: usize) {
+    *BUFFER.lock().unwrap() = PixelBuffer::new(w, h);
+    GENERATION.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Bumped each time the framebuffer is reallocated, a cached `get_buffer` result is stale
+/// once this differs from its `generation`.
+#[wasm_bindgen]
+pub fn buffer_generation() -> u32 {
+    GENERATION.load(Ordering::Relaxed)
 }
 
 #[wasm_bindgen]
 pub fn get_buffer() -> JSPixelBuffer {
     let g = BUFFER.lock().unwrap();
-    let addr = g.data.as_ptr() as usize;
-    JSPixelBuffer::new(addr, g.data.len(), g.width, g.height)
+    let bytes = g.as_bytes();
+    JSPixelBuffer::new(
+        bytes.as_ptr() as usize,
+        bytes.len(),
+        g.width,
+        g.height,
+        buffer_generation(),
+    )
 }
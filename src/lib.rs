@@ -1,6 +1,8 @@
 pub mod core;
 pub mod js;
 
+// core and js both have game, demo, audio and pixel_buf modules, only their items matter
+#[allow(ambiguous_glob_reexports)]
 pub use core::*;
 pub use js::*;
 
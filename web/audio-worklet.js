@@ -0,0 +1,30 @@
+// Plays the interleaved stereo frames mixed by wasm and posted from the main thread.
+class WolfensteinerAudio extends AudioWorkletProcessor {
+    constructor() {
+        super();
+        this.chunks = [];
+        this.offset = 0;
+        this.port.onmessage = (evt) => this.chunks.push(evt.data);
+    }
+
+    process(inputs, outputs) {
+        const [left, right] = outputs[0];
+        for (let i = 0; i < left.length; i++) {
+            const chunk = this.chunks[0];
+            if (!chunk) {
+                left[i] = right[i] = 0;
+                continue;
+            }
+            left[i] = chunk[this.offset];
+            right[i] = chunk[this.offset + 1];
+            this.offset += 2;
+            if (this.offset >= chunk.length) {
+                this.chunks.shift();
+                this.offset = 0;
+            }
+        }
+        return true;
+    }
+}
+
+registerProcessor('wolfensteiner-audio', WolfensteinerAudio);
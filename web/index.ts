@@ -10,16 +10,94 @@ import init, * as wasm from './node_modules/wolfensteiner/wolfensteiner.js';
         canvas!.addEventListener('mousemove', (evt: MouseEvent) => {
             wasm.move_mouse(evt.movementX, evt.movementY);
         });
+        const keys = new Set<string>();
+        const updateInput = () => {
+            const axis = (pos: string[], neg: string[]) =>
+                (pos.some(k => keys.has(k)) ? 127 : 0) - (neg.some(k => keys.has(k)) ? 127 : 0);
+            const buttons = (keys.has('ControlLeft') ? 1 : 0)
+                | (keys.has('Space') ? 2 : 0)
+                | (keys.has('ShiftLeft') ? 4 : 0);
+            wasm.set_input(axis(['KeyW', 'ArrowUp'], ['KeyS', 'ArrowDown']), axis(['KeyD'], ['KeyA']), buttons);
+        };
+        window.addEventListener('keydown', (evt: KeyboardEvent) => {
+            switch (evt.code) {
+                case 'Tab': evt.preventDefault(); wasm.toggle_automap(); break;
+                case 'Equal': wasm.zoom_automap(1.25); break;
+                case 'Minus': wasm.zoom_automap(0.8); break;
+                case 'KeyI': wasm.pan_automap(0, -16); break;
+                case 'KeyK': wasm.pan_automap(0, 16); break;
+                case 'KeyJ': wasm.pan_automap(-16, 0); break;
+                case 'KeyL': wasm.pan_automap(16, 0); break;
+                case 'KeyC': wasm.recenter_automap(); break;
+                case 'KeyF': canvas!.requestFullscreen(); break;
+            }
+            keys.add(evt.code);
+            updateInput();
+        });
+        window.addEventListener('keyup', (evt: KeyboardEvent) => { keys.delete(evt.code); updateInput(); });
         ctx.fillStyle = '#000';
         ctx.fillRect(0, 0, canvas!.width, canvas!.height);
-        wasm.init_game(canvas!.width, canvas!.height);
-        const pixel_buf = wasm.get_buffer();
+        const difficulty: number = parseInt(new URLSearchParams(location.search).get('difficulty') ?? '2');
+        const seed = Math.floor(Math.random() * 0x100000000);
+        wasm.init_game(canvas!.width, canvas!.height, difficulty, seed);
+        // `?scale=320x200` renders the world at a lower resolution, upscaled to the canvas
+        const scale = new URLSearchParams(location.search).get('scale')?.split('x').map(v => parseInt(v));
+        if (scale && scale.length === 2) {
+            wasm.set_render_scale(scale[0], scale[1], 1);
+        }
+        // view over the framebuffer in wasm memory, recreated only when the framebuffer
+        // is reallocated or the memory grows (which detaches the old ArrayBuffer)
+        let pixel_buf = wasm.get_buffer();
+        let frame: ImageData | null = null;
+        const currentFrame = (): ImageData => {
+            if (!frame || frame.data.buffer !== memory.buffer || pixel_buf.generation !== wasm.buffer_generation()) {
+                pixel_buf = wasm.get_buffer();
+                const view = new Uint8ClampedArray(memory.buffer, pixel_buf.addr, pixel_buf.len);
+                frame = new ImageData(view, pixel_buf.width, pixel_buf.height);
+            }
+            return frame;
+        };
+        // `?fit=window` resizes the framebuffer with the window, `?fit=scale` keeps its
+        // resolution and lets the browser scale the canvas up
+        const fit = new URLSearchParams(location.search).get('fit');
+        const fitCanvas = () => {
+            if (fit === 'window') {
+                canvas!.width = window.innerWidth;
+                canvas!.height = window.innerHeight;
+                wasm.resize(canvas!.width, canvas!.height);
+            } else if (fit === 'scale') {
+                const scale = Math.min(window.innerWidth / canvas!.width, window.innerHeight / canvas!.height);
+                canvas!.style.width = `${Math.floor(canvas!.width * scale)}px`;
+                canvas!.style.height = `${Math.floor(canvas!.height * scale)}px`;
+            }
+        };
+        window.addEventListener('resize', fitCanvas);
+        fitCanvas();
+        let audio: AudioContext | null = null;
+        let audioNode: AudioWorkletNode | null = null;
+        canvas!.addEventListener('click', () => {
+            if (audio) {
+                return;
+            }
+            // browsers only allow audio to start after a user gesture
+            const ctx = new AudioContext();
+            audio = ctx;
+            ctx.audioWorklet.addModule('audio-worklet.js').then(() => {
+                audioNode = new AudioWorkletNode(ctx, 'wolfensteiner-audio', { outputChannelCount: [2] });
+                audioNode.connect(ctx.destination);
+                wasm.init_audio(ctx.sampleRate, 8);
+            });
+        });
         let startTime: number = 0;
         function animate() {
             const dt = performance.now() - startTime;
             wasm.render_game(dt);
-            const buf = new Uint8ClampedArray(memory.buffer.slice(pixel_buf.addr, pixel_buf.addr + pixel_buf.len));
-            ctx?.putImageData(new ImageData(buf, canvas!.width, canvas!.height), 0, 0);
+            if (audio && audioNode) {
+                const frames = new Float32Array(Math.ceil(audio.sampleRate * dt / 1000) * 2);
+                const len = wasm.pull_audio(frames);
+                audioNode.port.postMessage(frames.subarray(0, len));
+            }
+            ctx?.putImageData(currentFrame(), 0, 0);
             startTime = performance.now();
             requestAnimationFrame(animate);
         }